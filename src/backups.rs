@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::parse_timestamp;
+
+const BACKUP_PREFIX: &str = "apps-";
+const BACKUP_SUFFIX: &str = ".json";
+const BACKUP_ID_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+pub struct Backup {
+    pub id: String,
+    pub path: PathBuf,
+    pub taken_at: DateTime<Utc>,
+}
+
+/// Store `content` (the state about to be overwritten) as a new snapshot and
+/// drop the oldest ones beyond `retention`.
+pub fn create(backups_dir: &Path, content: &[u8], retention: usize) -> Result<()> {
+    if retention == 0 {
+        return Ok(());
+    }
+    fs::create_dir_all(backups_dir)
+        .with_context(|| format!("Failed to create backups directory {}", backups_dir.display()))?;
+
+    // Bump the timestamp on the rare collision so ids stay unique and sortable
+    let mut taken_at = Utc::now();
+    let mut path = backup_path(backups_dir, &taken_at);
+    while path.exists() {
+        taken_at += chrono::Duration::milliseconds(1);
+        path = backup_path(backups_dir, &taken_at);
+    }
+    fs::write(&path, content).with_context(|| format!("Failed to write backup {}", path.display()))?;
+
+    let backups = list(backups_dir)?;
+    if backups.len() > retention {
+        for backup in &backups[..backups.len() - retention] {
+            fs::remove_file(&backup.path)?;
+        }
+    }
+    Ok(())
+}
+
+/// All snapshots, oldest first.
+pub fn list(backups_dir: &Path) -> Result<Vec<Backup>> {
+    if !backups_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(backups_dir)? {
+        let path = entry?.path();
+        let Some(id) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix(BACKUP_PREFIX))
+            .and_then(|n| n.strip_suffix(BACKUP_SUFFIX))
+        else {
+            continue;
+        };
+        let Ok(taken_at) = NaiveDateTime::parse_from_str(id, BACKUP_ID_FORMAT) else {
+            continue;
+        };
+        backups.push(Backup {
+            id: id.to_string(),
+            taken_at: taken_at.and_utc(),
+            path: path.clone(),
+        });
+    }
+    backups.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(backups)
+}
+
+/// Find the snapshot for `at`: either an exact backup id or the latest
+/// snapshot taken at or before the given time.
+pub fn find(backups_dir: &Path, at: &str) -> Result<Backup> {
+    let backups = list(backups_dir)?;
+    if let Some(index) = backups.iter().position(|b| b.id == at) {
+        return Ok(backups.into_iter().nth(index).unwrap());
+    }
+
+    let at_time = parse_timestamp(at).ok_or_else(|| {
+        anyhow::anyhow!("Invalid timestamp '{}'. Use a backup id from `restore --list`, RFC 3339, or YYYY-MM-DD[ HH:MM:SS]", at)
+    })?;
+    backups
+        .into_iter()
+        .rev()
+        .find(|b| b.taken_at <= at_time)
+        .ok_or_else(|| anyhow::anyhow!("No backup found at or before {}", at))
+}
+
+fn backup_path(backups_dir: &Path, taken_at: &DateTime<Utc>) -> PathBuf {
+    backups_dir.join(format!("{}{}{}", BACKUP_PREFIX, taken_at.format(BACKUP_ID_FORMAT), BACKUP_SUFFIX))
+}
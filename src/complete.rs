@@ -0,0 +1,133 @@
+use clap::ValueEnum;
+use clap_complete::CompletionCandidate;
+use clap_complete::env::{Bash, Elvish, EnvCompleter, Fish, Zsh};
+use std::ffi::OsStr;
+use std::io;
+use std::path::PathBuf;
+
+use crate::{AppsData, DATA_FILE_OVERRIDE, matcher, peek_data};
+
+/// Environment variable that makes the binary answer a completion request
+/// instead of running a command; see `clap_complete::CompleteEnv`.
+pub const ENV_VAR: &str = "COMPLETE";
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Elvish,
+}
+
+/// The script that hooks completion for `apps-helper` into `shell`. It calls
+/// back into `exe` on every completion, so app names come from the registry.
+pub fn write_registration(shell: Shell, exe: &str, out: &mut dyn io::Write) -> io::Result<()> {
+    let completer: &dyn EnvCompleter = match shell {
+        Shell::Bash => &Bash,
+        Shell::Zsh => &Zsh,
+        Shell::Fish => &Fish,
+        Shell::Elvish => &Elvish,
+    };
+    completer.write_registration(ENV_VAR, "apps-helper", "apps-helper", exe, out)
+}
+
+/// App names and aliases for `current`: prefix matches first, then
+/// whatever the fuzzy matcher would accept, best first.
+pub fn app_names(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(data) = registry() else {
+        return Vec::new();
+    };
+    let current = current.to_string_lossy();
+
+    let mut names: Vec<(String, String)> = Vec::new();
+    for (key, app) in &data.apps {
+        if key.starts_with(current.as_ref()) {
+            names.push((key.clone(), "app".to_string()));
+        }
+        for alias in app.aliases.iter().filter(|a| a.starts_with(current.as_ref())) {
+            names.push((alias.clone(), format!("alias of {}", key)));
+        }
+    }
+    names.sort();
+    if !current.is_empty() {
+        for m in matcher::rank(&data, &current) {
+            if !names.iter().any(|(name, _)| name == m.key) {
+                names.push((m.key.to_string(), m.kind.to_string()));
+            }
+        }
+    }
+
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(i, (name, help))| CompletionCandidate::new(name).help(Some(help.into())).display_order(Some(i)))
+        .collect()
+}
+
+/// Profile types of the app named by `--get`, or every type when there is
+/// none.
+pub fn profile_types(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    let app_types: Option<Vec<crate::ProfileType>> = registry().and_then(|data| {
+        let key = resolved(&data, &arg_value("--get")?)?;
+        Some(data.apps[&key].profiles.iter().map(|p| p.profile_type).collect())
+    });
+
+    crate::ProfileType::value_variants()
+        .iter()
+        .filter(|t| app_types.as_ref().is_none_or(|types| types.contains(t)))
+        .filter_map(|t| t.to_possible_value())
+        .filter(|v| v.get_name().starts_with(current.as_ref()))
+        .map(|v| CompletionCandidate::new(v.get_name()))
+        .collect()
+}
+
+/// Aliases of the app named by `--get`, or of every app.
+pub fn aliases(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(data) = registry() else {
+        return Vec::new();
+    };
+    let current = current.to_string_lossy();
+    let key = arg_value("--get").and_then(|term| resolved(&data, &term));
+
+    let mut aliases: Vec<&String> = data
+        .apps
+        .iter()
+        .filter(|(k, _)| key.as_ref().is_none_or(|key| key == *k))
+        .flat_map(|(_, app)| &app.aliases)
+        .filter(|a| a.starts_with(current.as_ref()))
+        .collect();
+    aliases.sort();
+    aliases.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// The app `term` names, if that's clear without asking: completion can't
+/// stop to let the user pick.
+fn resolved(data: &AppsData, term: &str) -> Option<String> {
+    match matcher::resolve(data, term) {
+        matcher::Resolution::Found(key) => Some(key.to_string()),
+        _ => None,
+    }
+}
+
+/// The registry as the command line being completed would see it. Errors
+/// just mean no candidates: completion must never print them.
+fn registry() -> Option<AppsData> {
+    if let Some(path) = arg_value("--data-file") {
+        let _ = DATA_FILE_OVERRIDE.set(std::path::absolute(PathBuf::from(path)).ok()?);
+    }
+    peek_data().ok()
+}
+
+/// Value of `flag` on the command line being completed.
+fn arg_value(flag: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let prefix = format!("{}=", flag);
+    args.iter().enumerate().rev().find_map(|(i, arg)| {
+        if arg == flag {
+            args.get(i + 1).cloned()
+        } else {
+            arg.strip_prefix(&prefix).map(String::from)
+        }
+    })
+}
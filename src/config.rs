@@ -0,0 +1,132 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use crate::Opener;
+use crate::storage::{self, Backend};
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    pub backups: BackupConfig,
+    pub storage: StorageConfig,
+    pub git: GitConfig,
+    pub open: OpenConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Number of snapshots to keep in the backups directory; 0 disables backups
+    pub retention: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig { retention: 20 }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Backend used for the default data file; switch with `storage migrate`
+    pub backend: Backend,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitConfig {
+    /// Commit the data file after every change; turned on by `git init`
+    pub enabled: bool,
+    /// Remote that `sync` pulls from and pushes to
+    pub remote: String,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        GitConfig { enabled: false, remote: "origin".to_string() }
+    }
+}
+
+/// Commands `open` runs. In the templates `{path}` is the profile
+/// location, `{name}` the app and `{editor}` `$VISUAL` or `$EDITOR`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenConfig {
+    /// Opener used when neither `--with` nor the app picks one
+    pub default: Opener,
+    pub editor: String,
+    pub files: String,
+    pub terminal: String,
+    pub vscode: String,
+}
+
+impl OpenConfig {
+    pub fn template(&self, opener: Opener) -> &str {
+        match opener {
+            Opener::Editor => &self.editor,
+            Opener::Files => &self.files,
+            Opener::Terminal => &self.terminal,
+            Opener::Vscode => &self.vscode,
+        }
+    }
+}
+
+impl Default for OpenConfig {
+    fn default() -> Self {
+        let (files, terminal) = if cfg!(target_os = "macos") {
+            ("open {path}", "open -a Terminal {path}")
+        } else {
+            ("xdg-open {path}", "x-terminal-emulator")
+        };
+        OpenConfig {
+            default: Opener::Editor,
+            editor: "{editor} {path}".to_string(),
+            files: files.to_string(),
+            terminal: terminal.to_string(),
+            vscode: "code {path}".to_string(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        storage::write_atomic(path, content.as_bytes())
+    }
+
+    /// Set a dotted key such as `backups.retention`. The value is parsed as
+    /// JSON when possible and as a plain string otherwise.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let mut document = serde_json::to_value(&*self)?;
+        let slot = key
+            .split('.')
+            .try_fold(&mut document, |node, part| node.get_mut(part))
+            .ok_or_else(|| anyhow::anyhow!("Unknown config key: {}", key))?;
+
+        *slot = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+        *self = serde_json::from_value(document)
+            .map_err(|e| anyhow::anyhow!("Invalid value for {}: {}", key, e))?;
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Result<Value> {
+        let document = serde_json::to_value(self)?;
+        key.split('.')
+            .try_fold(&document, |node, part| node.get(part))
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unknown config key: {}", key))
+    }
+}
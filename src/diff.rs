@@ -0,0 +1,132 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::{App, AppProfile, AppsData, tmux};
+
+/// One human-readable difference between two versions of the registry.
+pub enum Change {
+    AppAdded(String),
+    AppRemoved(String),
+    FieldChanged { app: String, field: &'static str, old: String, new: String },
+    TaskAdded { app: String, task: String },
+    TaskRemoved { app: String, task: String },
+    ProfileAdded { app: String, profile: String },
+    ProfileRemoved { app: String, profile: String },
+    ProfileChanged { app: String, profile: String, field: &'static str, old: String, new: String },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::AppAdded(name) => write!(f, "+ app {}", name),
+            Change::AppRemoved(name) => write!(f, "- app {}", name),
+            Change::FieldChanged { app, field, old, new } => write!(f, "~ {}: {} {} -> {}", app, field, old, new),
+            Change::TaskAdded { app, task } => write!(f, "+ {}: task \"{}\"", app, task),
+            Change::TaskRemoved { app, task } => write!(f, "- {}: task \"{}\"", app, task),
+            Change::ProfileAdded { app, profile } => write!(f, "+ {}: profile {}", app, profile),
+            Change::ProfileRemoved { app, profile } => write!(f, "- {}: profile {}", app, profile),
+            Change::ProfileChanged { app, profile, field, old, new } => {
+                write!(f, "~ {}: {} profile {} {} -> {}", app, profile, field, old, new)
+            }
+        }
+    }
+}
+
+/// Compare two registries app by app, in name order.
+pub fn diff_data(old: &AppsData, new: &AppsData) -> Vec<Change> {
+    let names: BTreeSet<&String> = old.apps.keys().chain(new.apps.keys()).collect();
+    let mut changes = Vec::new();
+
+    for name in names {
+        match (old.apps.get(name), new.apps.get(name)) {
+            (None, Some(_)) => changes.push(Change::AppAdded(name.clone())),
+            (Some(_), None) => changes.push(Change::AppRemoved(name.clone())),
+            (Some(old_app), Some(new_app)) => diff_app(old_app, new_app, &mut changes),
+            (None, None) => {}
+        }
+    }
+
+    changes
+}
+
+fn diff_app(old: &App, new: &App, changes: &mut Vec<Change>) {
+    let app = new.name.clone();
+    let mut field = |field: &'static str, old: String, new: String| {
+        if old != new {
+            changes.push(Change::FieldChanged { app: app.clone(), field, old, new });
+        }
+    };
+
+    field("name", old.name.clone(), new.name.clone());
+    field("tags", format!("[{}]", old.tags.join(", ")), format!("[{}]", new.tags.join(", ")));
+    field("aliases", format!("[{}]", old.aliases.join(", ")), format!("[{}]", new.aliases.join(", ")));
+    field("github_repo", display_option(&old.github_repo), display_option(&new.github_repo));
+    field("opener", display_option(&old.opener.map(|o| format!("{:?}", o))), display_option(&new.opener.map(|o| format!("{:?}", o))));
+    field("layout", describe_layout(&old.layout), describe_layout(&new.layout));
+    field("created_at", old.created_at.clone(), new.created_at.clone());
+    field("updated_at", old.updated_at.clone(), new.updated_at.clone());
+
+    for task in list_difference(&old.tasks, &new.tasks) {
+        changes.push(Change::TaskRemoved { app: app.clone(), task });
+    }
+    for task in list_difference(&new.tasks, &old.tasks) {
+        changes.push(Change::TaskAdded { app: app.clone(), task });
+    }
+
+    for old_profile in &old.profiles {
+        match new.profiles.iter().find(|p| p.same_slot(old_profile)) {
+            Some(new_profile) => diff_profile(&app, old_profile, new_profile, changes),
+            None => changes.push(Change::ProfileRemoved { app: app.clone(), profile: describe_profile(old_profile) }),
+        }
+    }
+    for new_profile in &new.profiles {
+        if !old.profiles.iter().any(|p| p.same_slot(new_profile)) {
+            changes.push(Change::ProfileAdded { app: app.clone(), profile: describe_profile(new_profile) });
+        }
+    }
+}
+
+fn diff_profile(app: &str, old: &AppProfile, new: &AppProfile, changes: &mut Vec<Change>) {
+    let profile = match &new.machine_name {
+        Some(machine) => format!("{:?} ({})", new.profile_type, machine),
+        None => format!("{:?}", new.profile_type),
+    };
+    let mut field = |field: &'static str, old: String, new: String| {
+        if old != new {
+            changes.push(Change::ProfileChanged { app: app.to_string(), profile: profile.clone(), field, old, new });
+        }
+    };
+
+    field("location", old.location.display().to_string(), new.location.display().to_string());
+    field("notes", display_option(&old.notes), display_option(&new.notes));
+    field("active", old.active.to_string(), new.active.to_string());
+}
+
+fn describe_profile(profile: &AppProfile) -> String {
+    match &profile.machine_name {
+        Some(machine) => format!("{:?} ({}): {}", profile.profile_type, machine, profile.location.display()),
+        None => format!("{:?}: {}", profile.profile_type, profile.location.display()),
+    }
+}
+
+fn display_option(value: &Option<String>) -> String {
+    value.clone().unwrap_or_else(|| "(none)".to_string())
+}
+
+fn describe_layout(layout: &[tmux::Window]) -> String {
+    format!("[{}]", layout.iter().map(|w| w.to_string()).collect::<Vec<_>>().join("; "))
+}
+
+/// Items of `a` that are not in `b`, counting duplicates.
+fn list_difference(a: &[String], b: &[String]) -> Vec<String> {
+    let mut remaining: Vec<&String> = b.iter().collect();
+    let mut result = Vec::new();
+    for item in a {
+        if let Some(pos) = remaining.iter().position(|r| *r == item) {
+            remaining.remove(pos);
+        } else {
+            result.push(item.clone());
+        }
+    }
+    result
+}
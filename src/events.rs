@@ -0,0 +1,500 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use crate::{App, AppProfile, AppsData, ProfileType};
+
+/// One line of the append-only log.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Event {
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    #[serde(default)]
+    pub machine: Option<String>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    /// Full state at the point the log was started
+    Snapshot { apps: HashMap<String, App> },
+    AppAdded { app: App },
+    AppRemoved { app: String },
+    TaskAdded { app: String, task: String, updated_at: String },
+    TaskRemoved { app: String, task: String, updated_at: String },
+    TagsChanged { app: String, tags: Vec<String>, updated_at: String },
+    AliasesChanged { app: String, aliases: Vec<String>, updated_at: String },
+    ProfileAdded { app: String, profile: AppProfile, updated_at: String },
+    ProfileRemoved {
+        app: String,
+        profile_type: ProfileType,
+        #[serde(default)]
+        machine_name: Option<String>,
+        updated_at: String,
+    },
+    ProfileActivated {
+        app: String,
+        profile_type: ProfileType,
+        #[serde(default)]
+        machine_name: Option<String>,
+        updated_at: String,
+    },
+    /// Any other change to an app, recorded as its full new state
+    AppUpdated { app: App },
+}
+
+impl EventKind {
+    /// Key of the app this event is about; `None` for snapshots.
+    pub fn app(&self) -> Option<&str> {
+        match self {
+            EventKind::Snapshot { .. } => None,
+            EventKind::AppAdded { app } | EventKind::AppUpdated { app } => Some(&app.name),
+            EventKind::AppRemoved { app }
+            | EventKind::TaskAdded { app, .. }
+            | EventKind::TaskRemoved { app, .. }
+            | EventKind::TagsChanged { app, .. }
+            | EventKind::AliasesChanged { app, .. }
+            | EventKind::ProfileAdded { app, .. }
+            | EventKind::ProfileRemoved { app, .. }
+            | EventKind::ProfileActivated { app, .. } => Some(app),
+        }
+    }
+
+    pub fn touches(&self, app_name: &str) -> bool {
+        match self {
+            EventKind::Snapshot { apps } => apps.contains_key(app_name),
+            _ => self.app() == Some(app_name),
+        }
+    }
+
+    /// Short description, e.g. "add task to foo".
+    pub fn describe(&self) -> String {
+        match self {
+            EventKind::Snapshot { apps } => format!("start history with {} app(s)", apps.len()),
+            EventKind::AppAdded { app } => format!("add app {}", app.name),
+            EventKind::AppRemoved { app } => format!("remove app {}", app),
+            EventKind::TaskAdded { app, task, .. } => format!("add task to {}: {}", app, task),
+            EventKind::TaskRemoved { app, task, .. } => format!("remove task from {}: {}", app, task),
+            EventKind::TagsChanged { app, tags, .. } => format!("set tags of {} to [{}]", app, tags.join(", ")),
+            EventKind::AliasesChanged { app, aliases, .. } => format!("set aliases of {} to [{}]", app, aliases.join(", ")),
+            EventKind::ProfileAdded { app, profile, .. } => {
+                format!("add {:?} profile to {}: {}", profile.profile_type, app, profile.location.display())
+            }
+            EventKind::ProfileRemoved { app, profile_type, .. } => format!("remove {:?} profile from {}", profile_type, app),
+            EventKind::ProfileActivated { app, profile_type, .. } => format!("activate {:?} profile of {}", profile_type, app),
+            EventKind::AppUpdated { app } => format!("update {}", app.name),
+        }
+    }
+}
+
+pub struct EventLog {
+    path: PathBuf,
+}
+
+impl EventLog {
+    pub fn new(path: PathBuf) -> EventLog {
+        EventLog { path }
+    }
+
+    pub fn read_all(&self) -> Result<Vec<Event>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let file = File::open(&self.path)?;
+        let lines: Vec<String> = BufReader::new(file).lines().collect::<Result<_, _>>()?;
+        let last = lines.len().saturating_sub(1);
+
+        let mut events = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(event) => events.push(event),
+                // A torn final line from a crash mid-append is dropped
+                Err(_) if i == last => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Corrupt event on line {} of {}", i + 1, self.path.display()));
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    pub fn read_after(&self, seq: u64) -> Result<Vec<Event>> {
+        Ok(self.read_all()?.into_iter().filter(|e| e.seq > seq).collect())
+    }
+
+    /// Sequence number of the newest event, reading only the end of the file.
+    pub fn last_seq(&self) -> Result<Option<u64>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let mut file = File::open(&self.path)?;
+        let len = file.metadata()?.len();
+
+        const CHUNK: u64 = 8 * 1024;
+        let mut end = len;
+        let mut tail: Vec<u8> = Vec::new();
+        while end > 0 {
+            let start = end.saturating_sub(CHUNK);
+            let mut chunk = vec![0; (end - start) as usize];
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(&mut chunk)?;
+            chunk.extend_from_slice(&tail);
+            tail = chunk;
+            end = start;
+
+            // Only lines after the first newline are known to be complete
+            let complete = if end == 0 { &tail[..] } else {
+                match tail.iter().position(|&b| b == b'\n') {
+                    Some(pos) => &tail[pos + 1..],
+                    None => continue,
+                }
+            };
+            for line in complete.split(|&b| b == b'\n').rev() {
+                if let Ok(event) = serde_json::from_slice::<Event>(line) {
+                    return Ok(Some(event.seq));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Size of the log in bytes, to roll back to with `truncate`.
+    pub fn size(&self) -> Result<u64> {
+        match fs::metadata(&self.path) {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Drop the events appended since the log was `size` bytes long.
+    pub fn truncate(&self, size: u64) -> Result<()> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+        let file = OpenOptions::new().write(true).open(&self.path)?;
+        file.set_len(size)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    pub fn append(&self, events: &[Event]) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).read(true).append(true).open(&self.path)?;
+        // Drop a torn line left by a crash so the new events start cleanly
+        let len = file.metadata()?.len();
+        let complete = complete_len(&mut file, len)?;
+        if complete < len {
+            file.set_len(complete)?;
+        }
+
+        let mut buffer = Vec::new();
+        for event in events {
+            serde_json::to_writer(&mut buffer, event)?;
+            buffer.push(b'\n');
+        }
+        file.write_all(&buffer)?;
+        file.sync_all()
+            .with_context(|| format!("Failed to write event log {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+/// Length of `file` up to and including its last newline.
+fn complete_len(file: &mut File, len: u64) -> Result<u64> {
+    const CHUNK: u64 = 8 * 1024;
+    let mut end = len;
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK);
+        let mut chunk = vec![0; (end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk)?;
+        if let Some(pos) = chunk.iter().rposition(|&b| b == b'\n') {
+            return Ok(start + pos as u64 + 1);
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Apply one event to the projection.
+pub fn apply(data: &mut AppsData, event: &EventKind) {
+    match event {
+        EventKind::Snapshot { apps } => data.apps = apps.clone(),
+        EventKind::AppAdded { app } | EventKind::AppUpdated { app } => {
+            data.apps.insert(app.name.clone(), app.clone());
+        }
+        EventKind::AppRemoved { app } => {
+            data.apps.remove(app);
+        }
+        EventKind::TaskAdded { app, task, updated_at } => {
+            if let Some(app) = data.apps.get_mut(app) {
+                app.tasks.push(task.clone());
+                app.updated_at = updated_at.clone();
+            }
+        }
+        EventKind::TaskRemoved { app, task, updated_at } => {
+            if let Some(app) = data.apps.get_mut(app) {
+                if let Some(pos) = app.tasks.iter().position(|t| t == task) {
+                    app.tasks.remove(pos);
+                }
+                app.updated_at = updated_at.clone();
+            }
+        }
+        EventKind::TagsChanged { app, tags, updated_at } => {
+            if let Some(app) = data.apps.get_mut(app) {
+                app.tags = tags.clone();
+                app.updated_at = updated_at.clone();
+            }
+        }
+        EventKind::AliasesChanged { app, aliases, updated_at } => {
+            if let Some(app) = data.apps.get_mut(app) {
+                app.aliases = aliases.clone();
+                app.updated_at = updated_at.clone();
+            }
+        }
+        EventKind::ProfileAdded { app, profile, updated_at } => {
+            if let Some(app) = data.apps.get_mut(app) {
+                app.profiles.push(profile.clone());
+                app.updated_at = updated_at.clone();
+            }
+        }
+        EventKind::ProfileRemoved { app, profile_type, machine_name, updated_at } => {
+            if let Some(app) = data.apps.get_mut(app) {
+                app.profiles.retain(|p| !(p.profile_type == *profile_type && p.machine_name == *machine_name));
+                if !app.profiles.is_empty() && !app.profiles.iter().any(|p| p.active) {
+                    app.profiles[0].active = true;
+                }
+                app.updated_at = updated_at.clone();
+            }
+        }
+        EventKind::ProfileActivated { app, profile_type, machine_name, updated_at } => {
+            if let Some(app) = data.apps.get_mut(app) {
+                for profile in &mut app.profiles {
+                    profile.active = profile.profile_type == *profile_type && profile.machine_name == *machine_name;
+                }
+                app.updated_at = updated_at.clone();
+            }
+        }
+    }
+}
+
+/// Replay events from an empty registry.
+pub fn replay<'a>(events: impl IntoIterator<Item = &'a Event>) -> AppsData {
+    let mut data = AppsData::default();
+    for event in events {
+        apply(&mut data, &event.kind);
+        data.event_seq = Some(event.seq);
+    }
+    data
+}
+
+/// Work out the events that turn `old` into `new`. Changes that don't map
+/// onto a specific event are recorded as the app's full new state.
+pub fn derive(old: &AppsData, new: &AppsData) -> Vec<EventKind> {
+    let names: BTreeSet<&String> = old.apps.keys().chain(new.apps.keys()).collect();
+    let mut events = Vec::new();
+
+    for name in names {
+        match (old.apps.get(name), new.apps.get(name)) {
+            (None, Some(app)) => events.push(EventKind::AppAdded { app: app.clone() }),
+            (Some(_), None) => events.push(EventKind::AppRemoved { app: name.clone() }),
+            (Some(old_app), Some(new_app)) => events.extend(derive_app(name, old_app, new_app)),
+            (None, None) => {}
+        }
+    }
+
+    events
+}
+
+fn derive_app(key: &str, old: &App, new: &App) -> Vec<EventKind> {
+    if old == new {
+        return Vec::new();
+    }
+
+    let app = key.to_string();
+    let updated_at = new.updated_at.clone();
+    let mut events = Vec::new();
+
+    if old.tags != new.tags {
+        events.push(EventKind::TagsChanged { app: app.clone(), tags: new.tags.clone(), updated_at: updated_at.clone() });
+    }
+    if old.aliases != new.aliases {
+        events.push(EventKind::AliasesChanged { app: app.clone(), aliases: new.aliases.clone(), updated_at: updated_at.clone() });
+    }
+
+    let mut remaining = new.tasks.clone();
+    for task in &old.tasks {
+        match remaining.iter().position(|t| t == task) {
+            Some(pos) => {
+                remaining.remove(pos);
+            }
+            None => events.push(EventKind::TaskRemoved { app: app.clone(), task: task.clone(), updated_at: updated_at.clone() }),
+        }
+    }
+    for task in remaining {
+        events.push(EventKind::TaskAdded { app: app.clone(), task, updated_at: updated_at.clone() });
+    }
+
+    for profile in &old.profiles {
+        if !new.profiles.iter().any(|p| p.same_slot(profile)) {
+            events.push(EventKind::ProfileRemoved {
+                app: app.clone(),
+                profile_type: profile.profile_type,
+                machine_name: profile.machine_name.clone(),
+                updated_at: updated_at.clone(),
+            });
+        }
+    }
+    for profile in &new.profiles {
+        if !old.profiles.iter().any(|p| p.same_slot(profile)) {
+            events.push(EventKind::ProfileAdded { app: app.clone(), profile: profile.clone(), updated_at: updated_at.clone() });
+        }
+    }
+    let was_active = old.profiles.iter().find(|p| p.active);
+    let now_active = new.profiles.iter().find(|p| p.active);
+    if let Some(profile) = now_active
+        && was_active.is_none_or(|p| !p.same_slot(profile))
+    {
+        events.push(EventKind::ProfileActivated {
+            app: app.clone(),
+            profile_type: profile.profile_type,
+            machine_name: profile.machine_name.clone(),
+            updated_at: updated_at.clone(),
+        });
+    }
+
+    // Check the fine-grained events reproduce the new state exactly; if not
+    // (field edits, reordering), record the whole app instead
+    let mut check = AppsData::default();
+    check.apps.insert(app.clone(), old.clone());
+    for event in &events {
+        apply(&mut check, event);
+    }
+    if check.apps.get(key) != Some(new) || events.is_empty() {
+        return vec![EventKind::AppUpdated { app: new.clone() }];
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(value: serde_json::Value) -> App {
+        let mut fields = serde_json::json!({
+            "github_repo": null,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        });
+        fields.as_object_mut().unwrap().extend(value.as_object().unwrap().clone());
+        serde_json::from_value(fields).unwrap()
+    }
+
+    fn registry(apps: Vec<App>) -> AppsData {
+        let mut data = AppsData::default();
+        for app in apps {
+            data.apps.insert(app.name.clone(), app);
+        }
+        data
+    }
+
+    fn profile(profile_type: &str, location: &str, active: bool) -> serde_json::Value {
+        serde_json::json!({ "profile_type": profile_type, "location": location, "machine_name": null, "notes": null, "active": active })
+    }
+
+    fn before() -> AppsData {
+        registry(vec![
+            app(serde_json::json!({
+                "name": "notes",
+                "tags": ["rust"],
+                "tasks": ["write docs", "ship"],
+                "profiles": [profile("dev", "/src/notes", true)],
+            })),
+            app(serde_json::json!({ "name": "old" })),
+        ])
+    }
+
+    fn kinds(events: &[EventKind]) -> Vec<String> {
+        events.iter().map(|e| e.describe()).collect()
+    }
+
+    #[test]
+    fn derived_events_reproduce_the_change_when_applied() {
+        let old = before();
+        let mut new = old.clone();
+        new.apps.remove("old");
+        new.apps.insert("fresh".to_string(), app(serde_json::json!({ "name": "fresh" })));
+        let notes = new.apps.get_mut("notes").unwrap();
+        notes.tags = vec!["rust".to_string(), "cli".to_string()];
+        notes.tasks = vec!["ship".to_string(), "fix the build".to_string()];
+        notes.profiles.push(serde_json::from_value(profile("binary", "/usr/bin/notes", false)).unwrap());
+        for profile in &mut notes.profiles {
+            profile.active = !profile.active;
+        }
+        notes.updated_at = "2024-02-01T00:00:00Z".to_string();
+
+        let events = derive(&old, &new);
+        assert_eq!(kinds(&events), [
+            "add app fresh",
+            "set tags of notes to [rust, cli]",
+            "remove task from notes: write docs",
+            "add task to notes: fix the build",
+            "add Binary profile to notes: /usr/bin/notes",
+            "activate Binary profile of notes",
+            "remove app old",
+        ]);
+
+        let mut replayed = old.clone();
+        for event in &events {
+            apply(&mut replayed, event);
+        }
+        assert_eq!(replayed.apps, new.apps);
+    }
+
+    #[test]
+    fn other_field_edits_record_the_whole_app() {
+        let old = before();
+        let mut new = old.clone();
+        new.apps.get_mut("notes").unwrap().github_repo = Some("me/notes".to_string());
+
+        let events = derive(&old, &new);
+        assert_eq!(kinds(&events), ["update notes"]);
+        let mut replayed = old.clone();
+        apply(&mut replayed, &events[0]);
+        assert_eq!(replayed.apps, new.apps);
+    }
+
+    #[test]
+    fn nothing_changed_derives_nothing() {
+        assert!(derive(&before(), &before()).is_empty());
+    }
+
+    #[test]
+    fn replay_starts_from_the_snapshot_and_tracks_the_sequence() {
+        let at = Utc::now();
+        let log = [
+            Event { seq: 1, at, machine: None, kind: EventKind::Snapshot { apps: before().apps } },
+            Event { seq: 2, at, machine: None, kind: EventKind::AppRemoved { app: "old".to_string() } },
+        ];
+        let data = replay(&log);
+        assert_eq!(data.event_seq, Some(2));
+        assert_eq!(data.apps.keys().collect::<Vec<_>>(), ["notes"]);
+    }
+}
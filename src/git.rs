@@ -0,0 +1,169 @@
+use anyhow::{Context, Result};
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A git working tree, driven through the `git` binary.
+pub struct Repo {
+    dir: PathBuf,
+    has_identity: OnceCell<bool>,
+}
+
+impl Repo {
+    pub fn new(dir: &Path) -> Repo {
+        Repo { dir: dir.to_path_buf(), has_identity: OnceCell::new() }
+    }
+
+    /// Whether the directory is the top of its own repository (not just
+    /// somewhere inside another one).
+    pub fn is_repo(&self) -> bool {
+        self.dir.join(".git").exists()
+    }
+
+    pub fn init(&self) -> Result<()> {
+        self.run(&["init", "--quiet"])?;
+        Ok(())
+    }
+
+    pub fn has_remote(&self, name: &str) -> Result<bool> {
+        Ok(self.run(&["remote"])?.lines().any(|line| line == name))
+    }
+
+    pub fn add_remote(&self, name: &str, url: &str) -> Result<()> {
+        self.run(&["remote", "add", name, url])?;
+        Ok(())
+    }
+
+    /// Stage `paths` and commit them. Returns false if there was nothing to commit.
+    pub fn commit(&self, paths: &[&str], message: &str) -> Result<bool> {
+        let mut add = vec!["add", "--"];
+        add.extend_from_slice(paths);
+        self.run(&add)?;
+
+        if self.status(&["diff", "--cached", "--quiet"])? {
+            return Ok(false);
+        }
+        self.run(&["commit", "--quiet", "-m", message])?;
+        Ok(true)
+    }
+
+    /// Conclude a merge started with `merge_ours`, whatever is staged.
+    pub fn commit_merge(&self, paths: &[&str], message: &str) -> Result<()> {
+        let mut add = vec!["add", "--"];
+        add.extend_from_slice(paths);
+        self.run(&add)?;
+        self.run(&["commit", "--quiet", "-m", message])?;
+        Ok(())
+    }
+
+    pub fn current_branch(&self) -> Result<String> {
+        self.run(&["symbolic-ref", "--short", "HEAD"])
+            .context("The data directory is not on a branch")
+    }
+
+    /// The branch checked out in the working tree around the directory, or
+    /// the short commit id when detached. `None` outside a working tree.
+    pub fn head_name(&self) -> Option<String> {
+        self.run(&["symbolic-ref", "--short", "--quiet", "HEAD"])
+            .or_else(|_| self.run(&["rev-parse", "--short", "HEAD"]))
+            .ok()
+    }
+
+    /// Fetch `branch` from `remote` into FETCH_HEAD. Returns false if the
+    /// remote doesn't have the branch yet.
+    pub fn fetch(&self, remote: &str, branch: &str) -> Result<bool> {
+        let output = self.output(&["ls-remote", "--exit-code", "--heads", remote, branch])?;
+        match output.status.code() {
+            Some(0) => {}
+            Some(2) => return Ok(false),
+            _ => return Err(failure(&["ls-remote", remote], &output)),
+        }
+        self.run(&["fetch", "--quiet", remote, branch])?;
+        Ok(true)
+    }
+
+    pub fn is_ancestor(&self, ancestor: &str, rev: &str) -> Result<bool> {
+        self.status(&["merge-base", "--is-ancestor", ancestor, rev])
+    }
+
+    /// Contents of `path` at `rev`, or `None` if it doesn't exist there.
+    pub fn show(&self, rev: &str, path: &str) -> Result<Option<String>> {
+        let spec = format!("{}:{}", rev, path);
+        if !self.status(&["cat-file", "-e", &spec])? {
+            return Ok(None);
+        }
+        Ok(Some(self.run(&["show", &spec])?))
+    }
+
+    pub fn merge_ff_only(&self, rev: &str) -> Result<()> {
+        self.run(&["merge", "--quiet", "--ff-only", rev])?;
+        Ok(())
+    }
+
+    /// Start a merge of `rev` that keeps our tree, leaving the real work of
+    /// combining the files to the caller before `commit_merge`.
+    pub fn merge_ours(&self, rev: &str) -> Result<()> {
+        self.run(&["merge", "--quiet", "-s", "ours", "--no-commit", "--allow-unrelated-histories", rev])?;
+        Ok(())
+    }
+
+    pub fn abort_merge(&self) -> Result<()> {
+        self.run(&["merge", "--abort"])?;
+        Ok(())
+    }
+
+    pub fn push(&self, remote: &str, branch: &str) -> Result<()> {
+        self.run(&["push", "--quiet", "-u", remote, branch])?;
+        Ok(())
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(&self.dir);
+        // Commits need an identity; fall back to one rather than failing on
+        // machines where git was never configured
+        if !self.has_identity() {
+            command.args(["-c", "user.name=apps-helper", "-c", "user.email=apps-helper@localhost"]);
+        }
+        command.args(args);
+        command
+    }
+
+    fn has_identity(&self) -> bool {
+        *self.has_identity.get_or_init(|| {
+            Command::new("git")
+                .arg("-C")
+                .arg(&self.dir)
+                .args(["config", "user.email"])
+                .output()
+                .is_ok_and(|output| output.status.success())
+        })
+    }
+
+    fn output(&self, args: &[&str]) -> Result<Output> {
+        self.command(args).output().context("Failed to run git. Is it installed?")
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String> {
+        let output = self.output(args)?;
+        if !output.status.success() {
+            return Err(failure(args, &output));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+
+    /// Run a command whose exit status is the answer: 0 is true, 1 is false.
+    fn status(&self, args: &[&str]) -> Result<bool> {
+        let output = self.output(args)?;
+        match output.status.code() {
+            Some(0) => Ok(true),
+            Some(1) => Ok(false),
+            _ => Err(failure(args, &output)),
+        }
+    }
+}
+
+fn failure(args: &[&str], output: &Output) -> anyhow::Error {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    anyhow::anyhow!("git {} failed: {}", args.join(" "), stderr.trim())
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use std::path::Path;
+use std::process::Command;
+
+/// The editor to open files with: `$VISUAL`, then `$EDITOR`, then `vi`.
+pub fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Split a command template into words as a shell would and fill in the
+/// placeholders. A `{path}` stays one argument even with spaces in it,
+/// while a bare `{editor}` may expand to several (`code --wait`).
+pub fn command_line(template: &str, path: &Path, name: &str, editor: &str) -> Result<Vec<String>> {
+    let words = shlex::split(template).ok_or_else(|| anyhow::anyhow!("Unbalanced quotes in command template: {}", template))?;
+    let path = path.to_string_lossy();
+    let mut args = Vec::new();
+    for word in words {
+        if word == "{editor}" {
+            args.extend(shlex::split(editor).unwrap_or_else(|| vec![editor.to_string()]));
+        } else {
+            args.push(word.replace("{path}", &path).replace("{name}", name).replace("{editor}", editor));
+        }
+    }
+    if args.is_empty() {
+        return Err(anyhow::anyhow!("Command template is empty"));
+    }
+    Ok(args)
+}
+
+/// Run `args` in `dir` and wait for it to finish.
+pub fn run(args: &[String], dir: &Path) -> Result<()> {
+    let status = Command::new(&args[0])
+        .args(&args[1..])
+        .current_dir(dir)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", args[0], e))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} exited with {}", args[0], status));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(template: &str, path: &str, editor: &str) -> Vec<String> {
+        command_line(template, Path::new(path), "notes", editor).unwrap()
+    }
+
+    #[test]
+    fn a_path_with_spaces_stays_one_argument() {
+        assert_eq!(line("code {path}", "/home/me/My Apps/notes", "vi"), ["code", "/home/me/My Apps/notes"]);
+        assert_eq!(line("open -a 'Visual Studio Code' {path}", "/a", "vi"), ["open", "-a", "Visual Studio Code", "/a"]);
+    }
+
+    #[test]
+    fn a_bare_editor_placeholder_splits_into_words() {
+        assert_eq!(line("{editor} {path}", "/a", "code --wait"), ["code", "--wait", "/a"]);
+        assert_eq!(line("{editor} {path}", "/a", "'/opt/My Editor/bin/ed'"), ["/opt/My Editor/bin/ed", "/a"]);
+        // Inside a longer word it is substituted as text
+        assert_eq!(line("sh -c '{editor} .'", "/a", "code --wait"), ["sh", "-c", "code --wait ."]);
+    }
+
+    #[test]
+    fn placeholders_inside_words_are_filled_in() {
+        assert_eq!(line("tmux new -s {name} -c {path}", "/a", "vi"), ["tmux", "new", "-s", "notes", "-c", "/a"]);
+        assert_eq!(line("term --dir={path} --title=\"{name} app\"", "/a b", "vi"), ["term", "--dir=/a b", "--title=notes app"]);
+    }
+
+    #[test]
+    fn bad_templates_are_errors() {
+        assert!(command_line("code '{path}", Path::new("/a"), "notes", "vi").is_err());
+        assert!(command_line("  ", Path::new("/a"), "notes", "vi").is_err());
+    }
+}
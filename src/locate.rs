@@ -0,0 +1,42 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{AppProfile, AppsData};
+
+/// An app found from a directory, and the profile the directory is in.
+pub struct Located<'a> {
+    pub key: &'a str,
+    pub profile: &'a AppProfile,
+}
+
+/// The app with a profile location at or above `dir`, preferring the
+/// deepest location so a nested crate wins over the workspace around it.
+/// Among equally deep locations, profiles of `machine` and then active
+/// profiles come first.
+pub fn find_by_dir<'a>(data: &'a AppsData, dir: &Path, machine: Option<&str>) -> Option<Located<'a>> {
+    let dir = canonical(dir);
+    let mut candidates: Vec<(usize, bool, bool, &str, &AppProfile)> = data
+        .apps
+        .iter()
+        .flat_map(|(key, app)| app.profiles.iter().map(move |profile| (key, profile)))
+        .filter_map(|(key, profile)| {
+            let location = canonical(&profile.location);
+            dir.starts_with(&location).then(|| {
+                let depth = location.components().count();
+                let on_machine = profile.machine_name.as_deref() == machine;
+                (depth, on_machine, profile.active, key.as_str(), profile)
+            })
+        })
+        .collect();
+    candidates.sort_by_key(|&(depth, on_machine, active, key, _)| (Reverse(depth), !on_machine, !active, key));
+    candidates.first().map(|&(_, _, _, key, profile)| Located { key, profile })
+}
+
+/// `path` with symlinks resolved. Locations that don't exist here (say,
+/// from another machine) are only made absolute.
+pub fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
@@ -1,13 +1,40 @@
 use anyhow::Result;
-use chrono::DateTime;
-use clap::{Parser, Subcommand, ValueEnum};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::ArgValueCompleter;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, ValueEnum, PartialEq)]
+mod backups;
+mod complete;
+mod config;
+mod diff;
+mod events;
+mod git;
+mod launch;
+mod locate;
+mod matcher;
+mod merge;
+mod migrations;
+mod paths;
+mod pick;
+mod prompt;
+mod query;
+mod search;
+mod shell;
+mod storage;
+mod tmux;
+mod transfer;
+mod validate;
+mod views;
+mod workspace;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ValueEnum, PartialEq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 enum ProfileType {
     Dev,       // Development repository
@@ -16,7 +43,17 @@ enum ProfileType {
     Config,    // Configuration files
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// How `open` shows an app's directory.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ValueEnum, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum Opener {
+    Editor,   // $VISUAL or $EDITOR
+    Files,    // File manager
+    Terminal, // Terminal emulator
+    Vscode,   // Visual Studio Code
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 struct AppProfile {
     profile_type: ProfileType,
     location: PathBuf,
@@ -25,30 +62,83 @@ struct AppProfile {
     active: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl AppProfile {
+    /// Profiles are identified by type and machine
+    fn same_slot(&self, other: &AppProfile) -> bool {
+        self.profile_type == other.profile_type && self.machine_name == other.machine_name
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 struct App {
     name: String,
     #[serde(default)]
     profiles: Vec<AppProfile>,
-    // Legacy field for migration
-    directory: Option<PathBuf>,
+    #[serde(default)]
     tags: Vec<String>,
+    // Other names the app can be looked up by
+    #[serde(default)]
+    aliases: Vec<String>,
     github_repo: Option<String>,
+    // Preferred opener for `open`, overriding open.default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    opener: Option<Opener>,
+    // tmux windows `tmux` sets up for the app
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    layout: Vec<tmux::Window>,
     #[serde(default)]
     tasks: Vec<String>,
+    #[schemars(extend("format" = "date-time"))]
     created_at: String,
+    #[schemars(extend("format" = "date-time"))]
     updated_at: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[schemars(title = "apps-helper registry")]
 struct AppsData {
+    #[serde(default)]
+    schema_version: u32,
+    // Sequence number of the last logged event this snapshot includes; kept
+    // by the store outside the document since it is local to this machine.
+    // None when the snapshot was changed outside apps-helper.
+    #[serde(skip)]
+    event_seq: Option<u64>,
+    #[serde(serialize_with = "serialize_sorted")]
     apps: HashMap<String, App>,
 }
 
+impl Default for AppsData {
+    fn default() -> Self {
+        AppsData {
+            schema_version: migrations::CURRENT_SCHEMA_VERSION,
+            event_seq: None,
+            apps: HashMap::new(),
+        }
+    }
+}
+
+// Stable key order keeps the file diffable
+fn serialize_sorted<S: serde::Serializer>(apps: &HashMap<String, App>, serializer: S) -> Result<S::Ok, S::Error> {
+    apps.iter().collect::<std::collections::BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// How the stored snapshot relates to the event log.
+enum SnapshotState {
+    Current,
+    /// Logged events were missing from the snapshot and have been applied
+    Behind,
+    /// The snapshot was edited outside apps-helper (by hand, or a git pull);
+    /// holds the state according to the log
+    External(AppsData),
+}
+
 #[derive(Parser)]
 #[command(name = "apps-helper")]
 #[command(about = "A CLI tool to manage your app usage and development")]
 struct Cli {
+    #[arg(long, global = true, value_name = "PATH", help = "Use this data file instead of the default location")]
+    data_file: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -56,8 +146,10 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     App {
-        #[arg(long, help = "Get specific app (supports fuzzy matching)")]
+        #[arg(long, add = ArgValueCompleter::new(complete::app_names), help = "Get specific app (supports fuzzy matching)")]
         get: Option<String>,
+        #[arg(long, conflicts_with = "get", help = "Use the app whose profile contains the current directory")]
+        current_dir: bool,
         #[command(subcommand)]
         subcommand: Option<AppCommands>,
     },
@@ -66,6 +158,289 @@ enum Commands {
         count: usize,
         #[arg(long, help = "Show each app on one line")]
         oneline: bool,
+        #[arg(long, value_name = "QUERY", help = WHERE_HELP)]
+        r#where: Option<String>,
+    },
+    #[command(about = "Upgrade the data file to the current schema version")]
+    Migrate {
+        #[arg(long, help = "Show what would change without writing anything")]
+        dry_run: bool,
+    },
+    #[command(about = "Roll back the most recent change")]
+    Undo,
+    #[command(about = "Restore the data from a backup snapshot")]
+    Restore {
+        #[arg(long, required_unless_present = "list", help = "Backup id or timestamp; restores the latest snapshot at or before it")]
+        at: Option<String>,
+        #[arg(long, help = "List available backups")]
+        list: bool,
+    },
+    #[command(about = "Show or change settings")]
+    Config {
+        #[command(subcommand)]
+        config_command: Option<ConfigCommands>,
+    },
+    #[command(about = "Show the history of changes, or the registry as it was at a point in time")]
+    Log {
+        #[arg(long, add = ArgValueCompleter::new(complete::app_names), help = "Only show events for this app (supports fuzzy matching)")]
+        app: Option<String>,
+        #[arg(short = 'n', long, help = "Show only the most recent events")]
+        limit: Option<usize>,
+        #[arg(long, conflicts_with = "limit", help = "Show the registry as it was at this time instead of the events")]
+        at: Option<String>,
+        #[arg(long, conflicts_with_all = ["app", "limit", "at"], help = "Rebuild the data file from the event log")]
+        rebuild: bool,
+        #[arg(short, long, requires = "rebuild", help = "Rebuild without asking for confirmation")]
+        yes: bool,
+    },
+    #[command(about = "Merge another apps-helper data file into this one")]
+    Merge {
+        #[arg(help = "Data file from another machine (apps.json or apps.db)")]
+        other: PathBuf,
+        #[arg(long, help = "Show what would change without writing anything")]
+        dry_run: bool,
+    },
+    #[command(about = "Manage the storage backend")]
+    Storage {
+        #[command(subcommand)]
+        storage_command: StorageCommands,
+    },
+    #[command(about = "Keep the data directory in a git repository")]
+    Git {
+        #[command(subcommand)]
+        git_command: GitCommands,
+    },
+    #[command(about = "Pull changes from the git remote, merge them and push")]
+    Sync,
+    #[command(about = "Write the registry as JSON, YAML, TOML or CSV")]
+    Export {
+        #[arg(long, value_enum, help = "Output format [default: from the output file extension, else json]")]
+        format: Option<transfer::Format>,
+        #[arg(short, long, value_name = "FILE", help = "Write to a file instead of stdout")]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "Read apps from a JSON, YAML, TOML or CSV export")]
+    #[command(group(clap::ArgGroup::new("mode").required(true).args(["merge", "replace"])))]
+    Import {
+        #[arg(help = "File to import, or - for stdin")]
+        file: PathBuf,
+        #[arg(long, value_enum, help = "Input format [default: from the file extension]")]
+        format: Option<transfer::Format>,
+        #[arg(long, help = "Combine with the existing apps; the more recently updated side wins")]
+        merge: bool,
+        #[arg(long, help = "Replace all existing apps with the imported ones")]
+        replace: bool,
+        #[arg(long, help = "Show what would change without writing anything")]
+        dry_run: bool,
+        #[arg(short, long, help = "Remove apps without asking for confirmation")]
+        yes: bool,
+    },
+    #[command(about = "Search names, aliases, tags, tasks, repos, locations and notes, best match first")]
+    Search {
+        #[arg(help = "Text to look for, ignoring case")]
+        query: String,
+        #[arg(long, help = "Treat the query as a case-sensitive regular expression")]
+        regex: bool,
+    },
+    #[command(about = "Apply one change to every app matching a query, after a single confirmation")]
+    Bulk {
+        #[arg(long, value_name = "QUERY", help = WHERE_HELP)]
+        r#where: String,
+        #[arg(long, global = true, help = "Show what would change without writing anything")]
+        dry_run: bool,
+        #[arg(short, long, global = true, help = "Apply without asking for confirmation")]
+        yes: bool,
+        #[command(subcommand)]
+        action: BulkAction,
+    },
+    #[command(about = "Run a saved view, or manage saved views")]
+    #[command(args_conflicts_with_subcommands = true)]
+    View {
+        #[arg(help = "Name of the view to run")]
+        name: Option<String>,
+        #[command(subcommand)]
+        view_command: Option<ViewCommands>,
+    },
+    #[command(about = "Show the app the current directory belongs to; exits 1 if there is none")]
+    Here {
+        #[arg(short, long, help = "Print nothing; only set the exit status")]
+        quiet: bool,
+    },
+    #[command(about = "Print a short prompt segment for the app of the current directory")]
+    Prompt {
+        #[arg(long, default_value = prompt::DEFAULT_FORMAT, help = "Placeholders: {name}, {type} (active profile), {inside} (profile we're in), {tasks}")]
+        format: String,
+        #[arg(long, value_enum, default_value = "none", help = "Escape the app name for this shell's prompt")]
+        shell: prompt::Shell,
+    },
+    #[command(about = "Print the location of an app's profile, for scripts")]
+    Path {
+        #[arg(add = ArgValueCompleter::new(complete::app_names), help = "App name (supports fuzzy matching)")]
+        name: String,
+        #[arg(long, value_enum, add = ArgValueCompleter::new(complete::profile_types), help = "Profile type [default: the active profile]")]
+        r#type: Option<ProfileType>,
+    },
+    #[command(about = "Open an app's profile location in the editor, file manager or terminal")]
+    Open {
+        #[arg(add = ArgValueCompleter::new(complete::app_names), help = "App name (supports fuzzy matching)")]
+        name: String,
+        #[arg(long, value_enum, add = ArgValueCompleter::new(complete::profile_types), help = "Profile type [default: the active profile]")]
+        r#type: Option<ProfileType>,
+        #[arg(long = "with", value_enum, help = "How to open it [default: the app's opener, else open.default]")]
+        with: Option<Opener>,
+    },
+    #[command(about = "Choose an app in an interactive fuzzy finder and print its name or path; exits 1 if cancelled")]
+    Pick {
+        #[arg(help = "Text to start filtering with")]
+        query: Option<String>,
+        #[arg(long, help = "Print the app's profile location instead of its name")]
+        path: bool,
+        #[arg(long, value_enum, requires = "path", add = ArgValueCompleter::new(complete::profile_types), help = "Profile type for --path [default: the active profile]")]
+        r#type: Option<ProfileType>,
+    },
+    #[command(about = "Create or attach to a tmux session for an app, set up with its layout")]
+    Tmux {
+        #[arg(add = ArgValueCompleter::new(complete::app_names), help = "App name (supports fuzzy matching)")]
+        name: String,
+        #[arg(short, long, help = "Create the session without attaching to it")]
+        detach: bool,
+    },
+    #[command(about = "Generate VS Code multi-root workspaces from apps")]
+    Workspace {
+        #[command(subcommand)]
+        workspace_command: WorkspaceCommands,
+    },
+    #[command(name = "shell-init", about = "Print a shell function that wraps apps-helper and adds `cd <app>`")]
+    ShellInit {
+        #[arg(value_enum)]
+        shell: shell::Shell,
+        #[arg(long, default_value = "ah", help = "Name of the function")]
+        name: String,
+        #[arg(long, value_name = "PATH", help = "Run this apps-helper binary instead of the one on the PATH")]
+        exe: Option<PathBuf>,
+    },
+    #[command(about = "Print a shell completion script that completes app names from the registry")]
+    Completions {
+        #[arg(value_enum)]
+        shell: complete::Shell,
+        #[arg(long, value_name = "PATH", help = "Complete with this apps-helper binary instead of the one on the PATH")]
+        exe: Option<PathBuf>,
+    },
+    #[command(about = "Print the JSON Schema of the data file")]
+    Schema,
+    #[command(about = "Check the data file and report every problem with its JSON path")]
+    Validate {
+        #[arg(help = "File to check instead of the data file")]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum BulkAction {
+    #[command(about = "Add or remove tags")]
+    Tag {
+        #[command(subcommand)]
+        tag_command: BulkTagCommands,
+    },
+    #[command(about = "Set a field on every matching app")]
+    Set {
+        #[arg(value_enum)]
+        field: BulkField,
+        #[arg(help = "New value; leave out to clear the field")]
+        value: Option<String>,
+    },
+    #[command(name = "add-task", about = "Add a task to every matching app")]
+    AddTask {
+        task: String,
+    },
+    #[command(about = "Remove every matching app")]
+    Remove,
+}
+
+#[derive(Subcommand)]
+enum BulkTagCommands {
+    Add {
+        #[arg(required = true, value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+    Remove {
+        #[arg(required = true, value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BulkField {
+    /// The app's GitHub repository
+    GithubRepo,
+    /// Notes on the app's active profile
+    Notes,
+}
+
+#[derive(Subcommand)]
+enum ViewCommands {
+    #[command(about = "Save a query and how to show its results under a name")]
+    Save {
+        name: String,
+        #[arg(long, value_name = "QUERY", help = WHERE_HELP)]
+        r#where: Option<String>,
+        #[arg(long, value_enum, help = "Order of the results [default: name]")]
+        sort: Option<views::SortKey>,
+        #[arg(long, value_enum, default_value = "full")]
+        format: views::ViewFormat,
+        #[arg(short = 'n', long, help = "Show at most this many apps")]
+        limit: Option<usize>,
+    },
+    List,
+    #[command(visible_alias = "remove")]
+    Rm {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkspaceCommands {
+    #[command(about = "Write a .code-workspace with the Dev location of each matching app, keeping the file's settings")]
+    Generate {
+        #[arg(long, value_name = "QUERY", help = WHERE_HELP)]
+        r#where: Option<String>,
+        #[arg(short, long, value_name = "FILE", help = "Write to a file instead of stdout")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum GitCommands {
+    #[command(about = "Turn the data directory into a git repository and commit every change")]
+    Init {
+        #[arg(long, help = "URL of the remote to sync with")]
+        remote: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum StorageCommands {
+    #[command(about = "Show the active backend and data file")]
+    Info,
+    #[command(about = "Copy all data to another backend and switch to it")]
+    Migrate {
+        #[arg(long, value_enum)]
+        to: storage::Backend,
+        #[arg(long, help = "Replace an existing file of the target backend, keeping it in the backups directory")]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    Show,
+    Get {
+        key: String,
+    },
+    Set {
+        #[arg(help = "Dotted key, e.g. backups.retention")]
+        key: String,
+        value: String,
     },
 }
 
@@ -80,10 +455,13 @@ enum AppCommands {
         #[arg(long, help = "Use current directory as app directory and derive name from directory name")]
         current_dir: bool,
     },
-    List,
+    List {
+        #[arg(long, value_name = "QUERY", help = WHERE_HELP)]
+        r#where: Option<String>,
+    },
     Get,
     Remove {
-        #[arg(long, help = "Remove app by name (supports fuzzy matching)")]
+        #[arg(long, add = ArgValueCompleter::new(complete::app_names), help = "Remove app by name (supports fuzzy matching)")]
         get: Option<String>,
         #[arg(long, help = "Remove app that matches current directory")]
         current_dir: bool,
@@ -96,12 +474,57 @@ enum AppCommands {
     AddTask {
         task: String,
     },
+    #[command(about = "Set how `open` shows the app; leave out to use open.default")]
+    Opener {
+        #[arg(value_enum)]
+        opener: Option<Opener>,
+    },
+    #[command(about = "Manage other names the app can be looked up by")]
+    Alias {
+        #[command(subcommand)]
+        alias_command: AliasCommands,
+    },
+    #[command(about = "Manage the tmux windows and panes `tmux` sets up")]
+    Layout {
+        #[command(subcommand)]
+        layout_command: LayoutCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum LayoutCommands {
+    #[command(about = "Add a pane to a window, adding the window if it's new")]
+    Add {
+        window: String,
+        #[arg(long, help = "Command to run in the pane; {editor}, {path} and {name} are filled in")]
+        run: Option<String>,
+        #[arg(long, value_enum, add = ArgValueCompleter::new(complete::profile_types), help = "Profile whose location the pane starts in [default: dev]")]
+        r#type: Option<ProfileType>,
+    },
+    #[command(about = "Remove a window and its panes")]
+    Remove {
+        window: String,
+    },
+    #[command(about = "Show the windows and the panes in each")]
+    List,
+}
+
+#[derive(Subcommand)]
+enum AliasCommands {
+    Add {
+        alias: String,
+    },
+    Remove {
+        #[arg(add = ArgValueCompleter::new(complete::aliases))]
+        alias: String,
+    },
+    List,
 }
 
 #[derive(Subcommand)]
 enum ProfileCommands {
     Add {
-        #[arg(long, value_enum)]
+        #[arg(long, value_enum, add = ArgValueCompleter::new(complete::profile_types))]
         r#type: ProfileType,
         #[arg(long)]
         location: Option<PathBuf>,
@@ -114,29 +537,204 @@ enum ProfileCommands {
     },
     List,
     Activate {
-        #[arg(long, value_enum)]
+        #[arg(long, value_enum, add = ArgValueCompleter::new(complete::profile_types))]
         r#type: ProfileType,
+        #[arg(long, help = "Machine of the profile, when several machines have this type")]
+        machine: Option<String>,
     },
     Remove {
-        #[arg(long, value_enum)]
+        #[arg(long, value_enum, add = ArgValueCompleter::new(complete::profile_types))]
         r#type: ProfileType,
+        #[arg(long, help = "Machine of the profile, when several machines have this type")]
+        machine: Option<String>,
     },
 }
 
-fn get_data_file_path() -> PathBuf {
-    let home = std::env::var("HOME").expect("HOME environment variable not set");
-    PathBuf::from(home).join(".apps-helper").join("apps.json")
+const WHERE_HELP: &str = "Only include apps matching an expression, e.g. 'tag:rust and not has:tasks' \
+    (fields: name alias tag repo task notes location machine type has created updated; \
+    ~ matches part of a value, dates take <7d or >2024-01-01)";
+
+// Set from --data-file; takes precedence over APPS_HELPER_HOME and the XDG location
+static DATA_FILE_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+fn get_data_file_path() -> Result<PathBuf> {
+    if let Some(path) = DATA_FILE_OVERRIDE.get() {
+        return Ok(path.clone());
+    }
+    let config = config::Config::load(&get_config_file_path()?)?;
+    Ok(paths::default_data_dir()?.join(config.storage.backend.file_name()))
+}
+
+fn get_lock_file_path() -> Result<PathBuf> {
+    let data_file = get_data_file_path()?;
+    let file_name = data_file.file_name().and_then(|n| n.to_str()).unwrap_or(paths::DATA_FILE_NAME);
+    Ok(data_file.with_file_name(format!("{}.lock", file_name)))
+}
+
+fn get_data_dir() -> Result<PathBuf> {
+    match DATA_FILE_OVERRIDE.get() {
+        Some(path) => Ok(path.parent().map(PathBuf::from).unwrap_or_default()),
+        None => paths::default_data_dir(),
+    }
+}
+
+/// `<stem>.<name>` beside the data file, so registries sharing a directory
+/// each keep their own. The stem rather than the file name, so it stays
+/// with the registry when it moves to another backend.
+fn data_file_sibling(name: &str) -> Result<PathBuf> {
+    let data_file = get_data_file_path()?;
+    let stem = data_file.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+    let path = data_file.with_file_name(format!("{}.{}", stem, name));
+    
+    // The default registry used to keep these under the bare name
+    if std::path::Path::new(paths::DATA_FILE_NAME).file_stem().and_then(|s| s.to_str()) == Some(stem.as_str()) {
+        let legacy = data_file.with_file_name(name);
+        if legacy.exists() && !path.exists() {
+            fs::rename(&legacy, &path).or_else(|e| if path.exists() { Ok(()) } else { Err(e) })?;
+        }
+    }
+    Ok(path)
+}
+
+fn get_backups_dir() -> Result<PathBuf> {
+    data_file_sibling("backups")
+}
+
+fn get_config_file_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("config.json"))
+}
+
+fn get_events_file_path() -> Result<PathBuf> {
+    data_file_sibling("events.jsonl")
+}
+
+fn get_prompt_index_path() -> Result<PathBuf> {
+    data_file_sibling("prompt-index.json")
+}
+
+fn get_views_file_path() -> Result<PathBuf> {
+    data_file_sibling("views.json")
 }
 
 fn main() -> Result<()> {
+    // Answer shell completion requests from `completions` scripts
+    clap_complete::CompleteEnv::with_factory(Cli::command).var(complete::ENV_VAR).complete();
+    
     let cli = Cli::parse();
+    
+    match cli.data_file {
+        Some(data_file) => {
+            let _ = DATA_FILE_OVERRIDE.set(std::path::absolute(data_file)?);
+        }
+        None => paths::migrate_legacy_data_dir()?,
+    }
 
     match cli.command {
-        Commands::App { get, subcommand } => {
+        Commands::App { get, current_dir, subcommand } => {
+            let get = if current_dir { Some(current_dir_app_key()?) } else { get };
             handle_app_command(get, subcommand)?;
         }
-        Commands::Latest { count, oneline } => {
-            list_latest_apps(count, oneline)?;
+        Commands::Latest { count, oneline, r#where } => {
+            list_latest_apps(count, oneline, r#where.as_deref())?;
+        }
+        Commands::Migrate { dry_run } => {
+            migrate_data(dry_run)?;
+        }
+        Commands::Undo => {
+            undo_last_change()?;
+        }
+        Commands::Restore { at, list } => {
+            if list {
+                list_backups()?;
+            } else if let Some(at) = at {
+                restore_backup(&at)?;
+            }
+        }
+        Commands::Config { config_command } => {
+            handle_config_command(config_command)?;
+        }
+        Commands::Log { app, limit, at, rebuild, yes } => {
+            if rebuild {
+                rebuild_from_log(yes)?;
+            } else if let Some(at) = at {
+                show_data_at(&at, app.as_deref())?;
+            } else {
+                show_log(app.as_deref(), limit)?;
+            }
+        }
+        Commands::Merge { other, dry_run } => {
+            merge_data_file(&other, dry_run)?;
+        }
+        Commands::Storage { storage_command } => {
+            handle_storage_command(storage_command)?;
+        }
+        Commands::Git { git_command: GitCommands::Init { remote } } => {
+            git_init(remote.as_deref())?;
+        }
+        Commands::Sync => {
+            sync_data()?;
+        }
+        Commands::Export { format, output } => {
+            export_data(format, output.as_deref())?;
+        }
+        Commands::Import { file, format, merge, dry_run, yes, .. } => {
+            import_data(&file, format, merge, dry_run, yes)?;
+        }
+        Commands::Search { query, regex } => {
+            search_apps(&query, regex)?;
+        }
+        Commands::Bulk { r#where, dry_run, yes, action } => {
+            bulk_update(&r#where, &action, dry_run, yes)?;
+        }
+        Commands::View { name, view_command } => {
+            handle_view_command(name.as_deref(), view_command)?;
+        }
+        Commands::Here { quiet } => {
+            if !show_here(quiet)? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Prompt { format, shell } => {
+            if !show_prompt(&format, shell)? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Path { name, r#type } => {
+            println!("{}", resolve_profile_location(&name, r#type)?.display());
+        }
+        Commands::Open { name, r#type, with } => {
+            open_app(&name, r#type, with)?;
+        }
+        Commands::Pick { query, path, r#type } => {
+            if !pick_app(query.as_deref().unwrap_or(""), path, r#type)? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Tmux { name, detach } => {
+            tmux_session(&name, detach)?;
+        }
+        Commands::Workspace { workspace_command } => match workspace_command {
+            WorkspaceCommands::Generate { r#where, output } => {
+                generate_workspace(r#where.as_deref(), output.as_deref())?;
+            }
+        },
+        Commands::ShellInit { shell, name, exe } => {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                return Err(anyhow::anyhow!("Function name '{}' may only contain letters, digits, '-' and '_'", name));
+            }
+            print!("{}", shell::init_script(shell, &name, exe.as_deref()));
+        }
+        Commands::Completions { shell, exe } => {
+            let exe = exe.map_or_else(|| "apps-helper".to_string(), |exe| exe.to_string_lossy().into_owned());
+            complete::write_registration(shell, &exe, &mut io::stdout())?;
+        }
+        Commands::Schema => {
+            println!("{}", serde_json::to_string_pretty(&schemars::schema_for!(AppsData))?);
+        }
+        Commands::Validate { file } => {
+            if !validate_data_file(file)? {
+                std::process::exit(1);
+            }
         }
     }
 
@@ -148,14 +746,14 @@ fn handle_app_command(get_app: Option<String>, subcommand: Option<AppCommands>)
         Some(AppCommands::Add { name, dir, tags, current_dir }) => {
             add_app(&name, &dir, &tags, current_dir)?;
         }
-        Some(AppCommands::List) => {
-            list_apps()?;
+        Some(AppCommands::List { r#where }) => {
+            list_apps(r#where.as_deref())?;
         }
         Some(AppCommands::Get) => {
             if let Some(app_name) = get_app {
                 get_app_info(&app_name)?;
             } else {
-                return Err(anyhow::anyhow!("--get is required for the get command"));
+                return Err(anyhow::anyhow!("--get or --current-dir is required for the get command"));
             }
         }
         Some(AppCommands::Remove { get, current_dir }) => {
@@ -166,14 +764,35 @@ fn handle_app_command(get_app: Option<String>, subcommand: Option<AppCommands>)
             if let Some(app_name) = get_app {
                 add_task(&app_name, &task)?;
             } else {
-                return Err(anyhow::anyhow!("--get is required for add-task command"));
+                return Err(anyhow::anyhow!("--get or --current-dir is required for add-task command"));
             }
         }
         Some(AppCommands::Profile { profile_command }) => {
             if let Some(app_name) = get_app {
                 handle_profile_command(&app_name, profile_command)?;
             } else {
-                return Err(anyhow::anyhow!("--get is required for profile commands"));
+                return Err(anyhow::anyhow!("--get or --current-dir is required for profile commands"));
+            }
+        }
+        Some(AppCommands::Opener { opener }) => {
+            if let Some(app_name) = get_app {
+                set_opener(&app_name, opener)?;
+            } else {
+                return Err(anyhow::anyhow!("--get or --current-dir is required for the opener command"));
+            }
+        }
+        Some(AppCommands::Alias { alias_command }) => {
+            if let Some(app_name) = get_app {
+                handle_alias_command(&app_name, alias_command)?;
+            } else {
+                return Err(anyhow::anyhow!("--get or --current-dir is required for alias commands"));
+            }
+        }
+        Some(AppCommands::Layout { layout_command }) => {
+            if let Some(app_name) = get_app {
+                handle_layout_command(&app_name, layout_command)?;
+            } else {
+                return Err(anyhow::anyhow!("--get or --current-dir is required for layout commands"));
             }
         }
         None => {
@@ -182,7 +801,7 @@ fn handle_app_command(get_app: Option<String>, subcommand: Option<AppCommands>)
                 // --get provided without subcommand, show app info
                 get_app_info(&app_name)?;
             } else {
-                return Err(anyhow::anyhow!("Please provide either --get <app-name> or a subcommand (add, list, remove, profile)"));
+                return Err(anyhow::anyhow!("Please provide either --get <app-name>, --current-dir or a subcommand (add, list, remove, profile)"));
             }
         }
     }
@@ -190,9 +809,11 @@ fn handle_app_command(get_app: Option<String>, subcommand: Option<AppCommands>)
 }
 
 fn handle_profile_command(app_name: &str, command: ProfileCommands) -> Result<()> {
+    let key = resolve_before_lock(app_name)?;
+    let _lock = lock_data()?;
     let mut data = load_data()?;
     
-    let app = find_app_by_name_mut(&mut data, app_name);
+    let app = data.apps.get_mut(&key);
     
     match app {
         Some(app) => {
@@ -216,16 +837,16 @@ fn handle_profile_command(app_name: &str, command: ProfileCommands) -> Result<()
                 ProfileCommands::List => {
                     list_profiles(app);
                 }
-                ProfileCommands::Activate { r#type } => {
+                ProfileCommands::Activate { r#type, machine } => {
                     let app_name = app.name.clone();
-                    activate_profile(app, r#type)?;
+                    activate_profile(app, r#type, machine.as_deref())?;
                     let _ = app; // Release the mutable borrow
                     save_data(&data)?;
                     println!("Activated {:?} profile for app: {}", r#type, app_name);
                 }
-                ProfileCommands::Remove { r#type } => {
+                ProfileCommands::Remove { r#type, machine } => {
                     let app_name = app.name.clone();
-                    remove_profile(app, r#type)?;
+                    remove_profile(app, r#type, machine.as_deref())?;
                     let _ = app; // Release the mutable borrow
                     save_data(&data)?;
                     println!("Removed {:?} profile from app: {}", r#type, app_name);
@@ -241,14 +862,15 @@ fn handle_profile_command(app_name: &str, command: ProfileCommands) -> Result<()
 }
 
 fn add_app(name: &Option<String>, dir: &Option<PathBuf>, tags: &Option<String>, use_current_dir: bool) -> Result<()> {
+    let _lock = lock_data()?;
     let mut data = load_data()?;
     
-    let tag_list = tags
+    let tag_list: Vec<String> = tags
         .as_ref()
         .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
-        .unwrap_or_else(Vec::new);
+        .unwrap_or_default();
 
-    let directory = if use_current_dir {
+    let location = if use_current_dir {
         Some(std::env::current_dir()?)
     } else {
         dir.clone()
@@ -274,6 +896,9 @@ fn add_app(name: &Option<String>, dir: &Option<PathBuf>, tags: &Option<String>,
     if data.apps.contains_key(&app_name) {
         return Err(anyhow::anyhow!("App '{}' already exists. Use a different name or remove the existing app first.", app_name));
     }
+    if let Some(owner) = find_name_owner(&data, &app_name, None) {
+        return Err(anyhow::anyhow!("'{}' is already an alias of {}. Use a different name.", app_name, owner));
+    }
 
     let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
     
@@ -282,7 +907,7 @@ fn add_app(name: &Option<String>, dir: &Option<PathBuf>, tags: &Option<String>,
     
     // Create initial profile if directory is specified
     let mut profiles = Vec::new();
-    if let Some(dir) = &directory {
+    if let Some(dir) = &location {
         profiles.push(AppProfile {
             profile_type: ProfileType::Dev,
             location: dir.clone(),
@@ -295,9 +920,11 @@ fn add_app(name: &Option<String>, dir: &Option<PathBuf>, tags: &Option<String>,
     let app = App {
         name: app_name.clone(),
         profiles: profiles.clone(),
-        directory: directory.clone(), // Keep for legacy compatibility
         tags: tag_list.clone(),
+        aliases: Vec::new(),
         github_repo: None,
+        opener: None,
+        layout: Vec::new(),
         tasks: Vec::new(),
         created_at: now.clone(),
         updated_at: now,
@@ -327,124 +954,69 @@ fn add_app(name: &Option<String>, dir: &Option<PathBuf>, tags: &Option<String>,
     Ok(())
 }
 
-fn list_apps() -> Result<()> {
+fn list_apps(filter: Option<&str>) -> Result<()> {
     let data = load_data()?;
+    let apps = filter_apps(&data, filter)?;
     
-    if data.apps.is_empty() {
+    if apps.is_empty() {
         println!("No apps found.");
         return Ok(());
     }
     
     println!("Apps:");
-    for (_, app) in &data.apps {
-        println!("  {}", app.name);
-        
-        // Show active profile or legacy directory
-        if let Some(active_profile) = app.profiles.iter().find(|p| p.active) {
-            println!("    {:?}: {}", active_profile.profile_type, active_profile.location.display());
-        } else if let Some(ref dir) = app.directory {
-            println!("    Directory: {}", dir.display());
-        }
-        
-        if !app.tags.is_empty() {
-            println!("    Tags: {}", app.tags.join(", "));
-        }
-        if let Some(ref repo) = app.github_repo {
-            println!("    GitHub: {}", repo);
-        }
-        if !app.tasks.is_empty() {
-            println!("    Tasks: {} task(s)", app.tasks.len());
-        }
-        println!("    Created: {}", format_datetime(&app.created_at));
-        println!();
+    for app in apps {
+        print_app_summary(app);
     }
     
     Ok(())
 }
 
-fn list_latest_apps(count: usize, oneline: bool) -> Result<()> {
+fn print_app_summary(app: &App) {
+    println!("  {}", app.name);
+    
+    // Show active profile
+    if let Some(active_profile) = app.profiles.iter().find(|p| p.active) {
+        println!("    {:?}: {}", active_profile.profile_type, active_profile.location.display());
+    }
+    
+    if !app.tags.is_empty() {
+        println!("    Tags: {}", app.tags.join(", "));
+    }
+    if let Some(ref repo) = app.github_repo {
+        println!("    GitHub: {}", repo);
+    }
+    if !app.tasks.is_empty() {
+        println!("    Tasks: {} task(s)", app.tasks.len());
+    }
+    println!("    Created: {}", format_datetime(&app.created_at));
+    println!();
+}
+
+fn list_latest_apps(count: usize, oneline: bool, filter: Option<&str>) -> Result<()> {
     let data = load_data()?;
+    let mut apps = filter_apps(&data, filter)?;
     
-    if data.apps.is_empty() {
+    if apps.is_empty() {
         println!("No apps found.");
         return Ok(());
     }
     
     // Sort apps by updated_at timestamp in descending order (latest first)
-    let mut apps: Vec<_> = data.apps.values().collect();
     apps.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
     
     let display_count = count.min(apps.len());
     
     if oneline {
-        // First pass: collect all the data and calculate max widths
-        let app_data: Vec<_> = apps.iter().take(display_count).map(|app| {
-            let location = if let Some(active_profile) = app.profiles.iter().find(|p| p.active) {
-                format!("({:?}: {})", active_profile.profile_type, active_profile.location.display())
-            } else if let Some(ref dir) = app.directory {
-                format!("({})", dir.display())
-            } else {
-                String::new()
-            };
-            
-            let tags = if !app.tags.is_empty() {
-                format!("[{}]", app.tags.join(", "))
-            } else {
-                String::new()
-            };
-            
-            let latest_task = if !app.tasks.is_empty() {
-                format!("Task: {}", app.tasks.last().unwrap())
-            } else {
-                String::new()
-            };
-            
-            let updated = format_datetime(&app.updated_at);
-            
-            (app.name.clone(), location, tags, latest_task, updated)
-        }).collect();
-        
-        // Calculate max widths for alignment
-        let max_name_width = app_data.iter().map(|(name, _, _, _, _)| name.len()).max().unwrap_or(0);
-        let max_location_width = app_data.iter().map(|(_, loc, _, _, _)| loc.len()).max().unwrap_or(0);
-        let max_tags_width = app_data.iter().map(|(_, _, tags, _, _)| tags.len()).max().unwrap_or(0);
-        let max_task_width = app_data.iter().map(|(_, _, _, task, _)| task.len()).max().unwrap_or(0);
-        
-        // Second pass: print with padding
-        for (name, location, tags, latest_task, updated) in app_data {
-            print!("{:<width$}", name, width = max_name_width + 2);
-            
-            if !location.is_empty() {
-                print!("{:<width$}", location, width = max_location_width + 2);
-            } else if max_location_width > 0 {
-                print!("{:<width$}", "", width = max_location_width + 2);
-            }
-            
-            if !tags.is_empty() {
-                print!("{:<width$}", tags, width = max_tags_width + 2);
-            } else if max_tags_width > 0 {
-                print!("{:<width$}", "", width = max_tags_width + 2);
-            }
-            
-            if !latest_task.is_empty() {
-                print!("{:<width$}", latest_task, width = max_task_width + 2);
-            } else if max_task_width > 0 {
-                print!("{:<width$}", "", width = max_task_width + 2);
-            }
-            
-            println!("{}", updated);
-        }
+        print_apps_oneline(&apps[..display_count]);
     } else {
         println!("Latest {} apps:", display_count);
         
         for app in apps.iter().take(display_count) {
             println!("  {}", app.name);
             
-            // Show active profile or legacy directory
+            // Show active profile
             if let Some(active_profile) = app.profiles.iter().find(|p| p.active) {
                 println!("    {:?}: {}", active_profile.profile_type, active_profile.location.display());
-            } else if let Some(ref dir) = app.directory {
-                println!("    Directory: {}", dir.display());
             }
             
             if !app.tags.is_empty() {
@@ -461,6 +1033,72 @@ fn list_latest_apps(count: usize, oneline: bool) -> Result<()> {
     Ok(())
 }
 
+/// One aligned line per app: name, active profile, tags, latest task and
+/// last update.
+fn print_apps_oneline(apps: &[&App]) {
+    // First pass: collect all the data and calculate max widths
+    let app_data: Vec<_> = apps.iter().map(|app| {
+        let location = if let Some(active_profile) = app.profiles.iter().find(|p| p.active) {
+            format!("({:?}: {})", active_profile.profile_type, active_profile.location.display())
+        } else {
+            String::new()
+        };
+        
+        let tags = if !app.tags.is_empty() {
+            format!("[{}]", app.tags.join(", "))
+        } else {
+            String::new()
+        };
+        
+        let latest_task = if !app.tasks.is_empty() {
+            format!("Task: {}", app.tasks.last().unwrap())
+        } else {
+            String::new()
+        };
+        
+        let updated = format_datetime(&app.updated_at);
+        
+        (app.name.clone(), location, tags, latest_task, updated)
+    }).collect();
+    
+    // Calculate max widths for alignment
+    let max_name_width = app_data.iter().map(|(name, _, _, _, _)| name.len()).max().unwrap_or(0);
+    let max_location_width = app_data.iter().map(|(_, loc, _, _, _)| loc.len()).max().unwrap_or(0);
+    let max_tags_width = app_data.iter().map(|(_, _, tags, _, _)| tags.len()).max().unwrap_or(0);
+    let max_task_width = app_data.iter().map(|(_, _, _, task, _)| task.len()).max().unwrap_or(0);
+    
+    // Second pass: print with padding
+    for (name, location, tags, latest_task, updated) in app_data {
+        print!("{:<width$}", name, width = max_name_width + 2);
+        
+        if !location.is_empty() {
+            print!("{:<width$}", location, width = max_location_width + 2);
+        } else if max_location_width > 0 {
+            print!("{:<width$}", "", width = max_location_width + 2);
+        }
+        
+        if !tags.is_empty() {
+            print!("{:<width$}", tags, width = max_tags_width + 2);
+        } else if max_tags_width > 0 {
+            print!("{:<width$}", "", width = max_tags_width + 2);
+        }
+        
+        if !latest_task.is_empty() {
+            print!("{:<width$}", latest_task, width = max_task_width + 2);
+        } else if max_task_width > 0 {
+            print!("{:<width$}", "", width = max_task_width + 2);
+        }
+        
+        println!("{}", updated);
+    }
+}
+
+/// The apps matching a `--where` expression, or all of them.
+fn filter_apps<'a>(data: &'a AppsData, filter: Option<&str>) -> Result<Vec<&'a App>> {
+    let query = filter.map(query::Query::parse).transpose()?;
+    Ok(data.apps.values().filter(|app| query.as_ref().is_none_or(|q| q.matches(app))).collect())
+}
+
 fn get_app_info(search_term: &str) -> Result<()> {
     let data = load_data()?;
     
@@ -469,7 +1107,7 @@ fn get_app_info(search_term: &str) -> Result<()> {
         return Ok(());
     }
     
-    let app = find_app_by_name(&data, search_term);
+    let app = find_app_by_name(&data, search_term)?;
     
     match app {
         Some(app) => {
@@ -490,19 +1128,24 @@ fn get_app_info(search_term: &str) -> Result<()> {
                 }
             }
             
-            // Show legacy directory if no profiles
-            if app.profiles.is_empty() {
-                if let Some(dir) = &app.directory {
-                    println!("  Directory: {}", dir.display());
-                }
-            }
-            
             if !app.tags.is_empty() {
                 println!("  Tags: {}", app.tags.join(", "));
             }
+            if !app.aliases.is_empty() {
+                println!("  Aliases: {}", app.aliases.join(", "));
+            }
             if let Some(ref repo) = app.github_repo {
                 println!("  GitHub: {}", repo);
             }
+            if let Some(opener) = app.opener {
+                println!("  Opener: {:?}", opener);
+            }
+            if !app.layout.is_empty() {
+                println!("  Layout:");
+                for window in &app.layout {
+                    println!("    - {}", window);
+                }
+            }
             if !app.tasks.is_empty() {
                 println!("  Tasks:");
                 for (i, task) in app.tasks.iter().enumerate() {
@@ -521,7 +1164,7 @@ fn get_app_info(search_term: &str) -> Result<()> {
 }
 
 fn remove_app(search_term: &Option<String>, use_current_dir: bool) -> Result<()> {
-    let mut data = load_data()?;
+    let data = load_data()?;
     
     if data.apps.is_empty() {
         println!("No apps found.");
@@ -532,7 +1175,7 @@ fn remove_app(search_term: &Option<String>, use_current_dir: bool) -> Result<()>
         find_app_by_current_dir(&data)?
     } else {
         match search_term {
-            Some(term) => find_app_by_name(&data, term),
+            Some(term) => find_app_by_exact_name(&data, term)?,
             None => return Err(anyhow::anyhow!("Either --get or --current-dir must be specified")),
         }
     };
@@ -543,8 +1186,6 @@ fn remove_app(search_term: &Option<String>, use_current_dir: bool) -> Result<()>
             
             if let Some(active_profile) = app.profiles.iter().find(|p| p.active) {
                 println!("  {:?}: {}", active_profile.profile_type, active_profile.location.display());
-            } else if let Some(dir) = &app.directory {
-                println!("  Directory: {}", dir.display());
             }
             
             if !app.tags.is_empty() {
@@ -552,16 +1193,14 @@ fn remove_app(search_term: &Option<String>, use_current_dir: bool) -> Result<()>
             }
             println!();
             
-            print!("Are you sure you want to remove this app? (y/N): ");
-            io::stdout().flush()?;
-            
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
-            let input = input.trim().to_lowercase();
-            
-            if input == "y" || input == "yes" {
+            if confirm("Are you sure you want to remove this app?")? {
                 let app_name = app.name.clone();
-                data.apps.remove(&app_name);
+                // Re-read under the lock: the data may have changed while we waited for confirmation
+                let _lock = lock_data()?;
+                let mut data = load_data()?;
+                if data.apps.remove(&app_name).is_none() {
+                    return Err(anyhow::anyhow!("App '{}' was removed by another process", app_name));
+                }
                 save_data(&data)?;
                 println!("Removed app: {}", app_name);
             } else {
@@ -581,9 +1220,11 @@ fn remove_app(search_term: &Option<String>, use_current_dir: bool) -> Result<()>
 }
 
 fn add_task(search_term: &str, task: &str) -> Result<()> {
+    let key = resolve_before_lock(search_term)?;
+    let _lock = lock_data()?;
     let mut data = load_data()?;
     
-    if let Some(app) = find_app_by_name_mut(&mut data, search_term) {
+    if let Some(app) = data.apps.get_mut(&key) {
         let app_name = app.name.clone(); // Clone the name before modifying
         app.tasks.push(task.to_string());
         app.updated_at = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
@@ -597,9 +1238,182 @@ fn add_task(search_term: &str, task: &str) -> Result<()> {
     Ok(())
 }
 
+fn set_opener(search_term: &str, opener: Option<Opener>) -> Result<()> {
+    let key = resolve_before_lock(search_term)?;
+    let _lock = lock_data()?;
+    let mut data = load_data()?;
+    
+    let app = data.apps.get_mut(&key).ok_or_else(|| anyhow::anyhow!("App '{}' not found", search_term))?;
+    if app.opener == opener {
+        println!("Nothing to change.");
+        return Ok(());
+    }
+    app.opener = opener;
+    app.updated_at = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let app_name = app.name.clone();
+    save_data(&data)?;
+    match opener {
+        Some(opener) => println!("✓ {} now opens with {:?}", app_name, opener),
+        None => println!("✓ {} now opens with the default opener", app_name),
+    }
+    Ok(())
+}
+
+fn handle_alias_command(app_name: &str, command: AliasCommands) -> Result<()> {
+    let key = resolve_before_lock(app_name)?;
+    let _lock = lock_data()?;
+    let mut data = load_data()?;
+    
+    if !data.apps.contains_key(&key) {
+        return Err(anyhow::anyhow!("App '{}' not found", app_name));
+    }
+    match command {
+        AliasCommands::Add { alias } => {
+            let alias = alias.trim().to_string();
+            if normalize_name(&alias).is_empty() {
+                return Err(anyhow::anyhow!("Alias must contain at least one letter or digit"));
+            }
+            if let Some(owner) = find_name_owner(&data, &alias, Some(&key)) {
+                return Err(anyhow::anyhow!("'{}' is already taken by {}", alias, owner));
+            }
+            let app = data.apps.get_mut(&key).expect("resolved app exists");
+            if app.aliases.iter().any(|a| same_name(a, &alias)) || same_name(&app.name, &alias) {
+                println!("{} is already known as {}.", app.name, alias);
+                return Ok(());
+            }
+            app.aliases.push(alias.clone());
+            app.updated_at = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+            save_data(&data)?;
+            println!("✓ Added alias {} for {}", alias, key);
+        }
+        AliasCommands::Remove { alias } => {
+            let app = data.apps.get_mut(&key).expect("resolved app exists");
+            let before = app.aliases.len();
+            app.aliases.retain(|a| !same_name(a, &alias));
+            if app.aliases.len() == before {
+                return Err(anyhow::anyhow!("{} has no alias '{}'", key, alias));
+            }
+            app.updated_at = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+            save_data(&data)?;
+            println!("✓ Removed alias {} from {}", alias, key);
+        }
+        AliasCommands::List => {
+            let app = &data.apps[&key];
+            if app.aliases.is_empty() {
+                println!("{} has no aliases.", app.name);
+            }
+            for alias in &app.aliases {
+                println!("{}", alias);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_layout_command(app_name: &str, command: LayoutCommands) -> Result<()> {
+    let key = resolve_before_lock(app_name)?;
+    let _lock = lock_data()?;
+    let mut data = load_data()?;
+    
+    let app = data.apps.get_mut(&key).ok_or_else(|| anyhow::anyhow!("App '{}' not found", app_name))?;
+    match command {
+        LayoutCommands::Add { window, run, r#type } => {
+            let window = window.trim().to_string();
+            if window.is_empty() {
+                return Err(anyhow::anyhow!("Window name cannot be empty"));
+            }
+            let pane = tmux::Pane { command: run.filter(|c| !c.trim().is_empty()), profile_type: r#type };
+            match app.layout.iter_mut().find(|w| w.name == window) {
+                Some(existing) => existing.panes.push(pane),
+                None => app.layout.push(tmux::Window { name: window.clone(), panes: vec![pane] }),
+            }
+            app.updated_at = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+            let added = app.layout.iter().find(|w| w.name == window).expect("window was just added").to_string();
+            save_data(&data)?;
+            println!("✓ {}", added);
+        }
+        LayoutCommands::Remove { window } => {
+            let before = app.layout.len();
+            app.layout.retain(|w| w.name != window);
+            if app.layout.len() == before {
+                return Err(anyhow::anyhow!("{} has no window '{}'", app.name, window));
+            }
+            app.updated_at = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+            let app_name = app.name.clone();
+            save_data(&data)?;
+            println!("✓ Removed window {} from {}", window, app_name);
+        }
+        LayoutCommands::List => {
+            if app.layout.is_empty() {
+                println!("{} has no layout; `tmux` opens a single shell.", app.name);
+            }
+            for window in &app.layout {
+                println!("{}", window);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The app other than `except` whose name or an alias is `name`.
+fn find_name_owner<'a>(data: &'a AppsData, name: &str, except: Option<&str>) -> Option<&'a str> {
+    data.apps
+        .iter()
+        .filter(|(key, _)| Some(key.as_str()) != except)
+        .find(|(key, app)| same_name(key, name) || same_name(&app.name, name) || app.aliases.iter().any(|a| same_name(a, name)))
+        .map(|(key, _)| key.as_str())
+}
+
+/// Names and aliases the resolver couldn't tell apart, each described
+/// as "'x' of b is already used by a".
+fn name_collisions(data: &AppsData) -> Vec<String> {
+    let mut keys: Vec<&String> = data.apps.keys().collect();
+    keys.sort();
+    // Names claim their spelling before any alias does
+    let mut names: Vec<(&str, &str)> = keys.iter().map(|key| (key.as_str(), key.as_str())).collect();
+    for key in &keys {
+        names.extend(data.apps[*key].aliases.iter().map(|alias| (key.as_str(), alias.as_str())));
+    }
+    
+    let mut owners: HashMap<String, &str> = HashMap::new();
+    let mut collisions = Vec::new();
+    for (key, name) in names {
+        match owners.get(&normalize_name(name)) {
+            Some(&owner) if owner != key => {
+                collisions.push(format!("'{}' of {} is already used by {}", name, key, owner));
+            }
+            Some(_) => {}
+            None => {
+                owners.insert(normalize_name(name), key);
+            }
+        }
+    }
+    collisions
+}
+
+/// Refuses a merged or imported registry in which two apps share a name
+/// or alias they didn't share in `before`, since lookups could then go
+/// either way.
+fn check_new_collisions(before: &AppsData, after: &AppsData) -> Result<()> {
+    let existing = name_collisions(before);
+    let new: Vec<String> = name_collisions(after).into_iter().filter(|c| !existing.contains(c)).collect();
+    if new.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "The result would give several apps the same name or alias; nothing was written:\n  {}\nRename the app or remove the alias on one side and try again.",
+        new.join("\n  ")
+    ))
+}
+
+/// Names the resolver can't tell apart.
+fn same_name(a: &str, b: &str) -> bool {
+    normalize_name(a) == normalize_name(b)
+}
+
 fn add_profile(app: &mut App, profile_type: ProfileType, location: PathBuf, machine: Option<String>, notes: Option<String>) -> Result<()> {
-    // Check if profile type already exists
-    if app.profiles.iter().any(|p| p.profile_type == profile_type) {
+    // Check if profile type already exists on this machine
+    if app.profiles.iter().any(|p| p.profile_type == profile_type && p.machine_name == machine) {
         return Err(anyhow::anyhow!("Profile type {:?} already exists for this app", profile_type));
     }
     
@@ -620,9 +1434,6 @@ fn add_profile(app: &mut App, profile_type: ProfileType, location: PathBuf, mach
 fn list_profiles(app: &App) {
     if app.profiles.is_empty() {
         println!("No profiles found for app: {}", app.name);
-        if let Some(ref dir) = app.directory {
-            println!("  Legacy directory: {}", dir.display());
-        }
         return;
     }
     
@@ -639,32 +1450,19 @@ fn list_profiles(app: &App) {
     }
 }
 
-fn activate_profile(app: &mut App, profile_type: ProfileType) -> Result<()> {
-    let mut found = false;
-    for profile in &mut app.profiles {
-        if profile.profile_type == profile_type {
-            profile.active = true;
-            found = true;
-        } else {
-            profile.active = false;
-        }
-    }
-    
-    if !found {
-        return Err(anyhow::anyhow!("Profile type {:?} not found for this app", profile_type));
+fn activate_profile(app: &mut App, profile_type: ProfileType, machine: Option<&str>) -> Result<()> {
+    let index = find_profile_index(app, profile_type, machine)?;
+    for (i, profile) in app.profiles.iter_mut().enumerate() {
+        profile.active = i == index;
     }
     
     app.updated_at = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
     Ok(())
 }
 
-fn remove_profile(app: &mut App, profile_type: ProfileType) -> Result<()> {
-    let initial_len = app.profiles.len();
-    app.profiles.retain(|p| p.profile_type != profile_type);
-    
-    if app.profiles.len() == initial_len {
-        return Err(anyhow::anyhow!("Profile type {:?} not found for this app", profile_type));
-    }
+fn remove_profile(app: &mut App, profile_type: ProfileType, machine: Option<&str>) -> Result<()> {
+    let index = find_profile_index(app, profile_type, machine)?;
+    app.profiles.remove(index);
     
     // If we removed the active profile, make the first remaining profile active
     if !app.profiles.is_empty() && !app.profiles.iter().any(|p| p.active) {
@@ -675,125 +1473,1302 @@ fn remove_profile(app: &mut App, profile_type: ProfileType) -> Result<()> {
     Ok(())
 }
 
+/// Index of the profile `--type`/`--machine` refer to. When several machines
+/// have a profile of that type, the current machine's is used unless
+/// `machine` picks one.
+fn find_profile_index(app: &App, profile_type: ProfileType, machine: Option<&str>) -> Result<usize> {
+    let candidates: Vec<usize> = app
+        .profiles
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.profile_type == profile_type)
+        .filter(|(_, p)| machine.is_none_or(|m| p.machine_name.as_deref() == Some(m)))
+        .map(|(i, _)| i)
+        .collect();
+    
+    match candidates.as_slice() {
+        [] => Err(anyhow::anyhow!("Profile type {:?} not found for this app", profile_type)),
+        [index] => Ok(*index),
+        _ => {
+            let current_machine = get_machine_name();
+            candidates
+                .iter()
+                .copied()
+                .find(|&i| app.profiles[i].machine_name == current_machine)
+                .ok_or_else(|| {
+                    let machines: Vec<String> = candidates
+                        .iter()
+                        .map(|&i| app.profiles[i].machine_name.clone().unwrap_or_else(|| "(none)".to_string()))
+                        .collect();
+                    anyhow::anyhow!(
+                        "{:?} profiles exist for several machines ({}); pick one with --machine",
+                        profile_type,
+                        machines.join(", ")
+                    )
+                })
+        }
+    }
+}
+
+fn open_store() -> Result<Box<dyn storage::Store>> {
+    Ok(storage::open(&get_data_file_path()?))
+}
+
+fn open_event_log() -> Result<events::EventLog> {
+    Ok(events::EventLog::new(get_events_file_path()?))
+}
+
 fn load_data() -> Result<AppsData> {
-    let data_file = get_data_file_path();
-    if !data_file.exists() {
-        return Ok(AppsData::default());
+    let (data, report, state) = read_current_data()?;
+    if !report.is_upgrade() && matches!(state, SnapshotState::Current) {
+        return Ok(data);
     }
     
-    let content = fs::read_to_string(&data_file)?;
-    let mut data: AppsData = serde_json::from_str(&content)?;
-    
-    // Migrate legacy directory field to profiles if needed
-    for app in data.apps.values_mut() {
-        if app.profiles.is_empty() && app.directory.is_some() {
-            app.profiles.push(AppProfile {
-                profile_type: ProfileType::Dev,
-                location: app.directory.as_ref().unwrap().clone(),
-                machine_name: None,
-                notes: None,
-                active: true,
-            });
+    // Older schema or a snapshot out of step with the log: bring it up to
+    // date once and write it back, re-reading under the lock
+    let _lock = lock_data()?;
+    let (mut data, report, state) = read_current_data()?;
+    if report.is_upgrade() {
+        save_data(&data)?;
+        eprintln!("Migrated {} from schema version {} to {}", get_data_file_path()?.display(), report.from_version, report.to_version);
+    } else {
+        match state {
+            SnapshotState::Current => {}
+            SnapshotState::Behind => open_store()?.save(&data)?,
+            SnapshotState::External(logged) => {
+                // Outside edits win; record them so the log catches up
+                let (seq, _) = record_events(&logged, &data)?;
+                data.event_seq = Some(seq);
+                open_store()?.save(&data)?;
+            }
         }
     }
-    
     Ok(data)
 }
 
+/// The registry as load_data would return it, without taking the lock or
+/// writing anything back; for completion, which runs on every keypress.
+fn peek_data() -> Result<AppsData> {
+    Ok(read_current_data()?.0)
+}
+
+/// The snapshot, brought up to date with any logged events it is missing.
+fn read_current_data() -> Result<(AppsData, migrations::MigrationReport, SnapshotState)> {
+    let store = open_store()?;
+    let (mut data, report) = if store.exists() {
+        store.load()?
+    } else {
+        let data = AppsData::default();
+        let report = migrations::MigrationReport::up_to_date(data.schema_version);
+        (data, report)
+    };
+    
+    let log = open_event_log()?;
+    let state = match (data.event_seq, log.last_seq()?) {
+        (_, None) => SnapshotState::Current,
+        (Some(seq), Some(last)) if seq == last => SnapshotState::Current,
+        (Some(seq), Some(last)) if seq < last => {
+            for event in log.read_after(seq)? {
+                events::apply(&mut data, &event.kind);
+                data.event_seq = Some(event.seq);
+            }
+            SnapshotState::Behind
+        }
+        _ => SnapshotState::External(events::replay(&log.read_all()?)),
+    };
+    Ok((data, report, state))
+}
+
 fn save_data(data: &AppsData) -> Result<()> {
-    let content = serde_json::to_string_pretty(data)?;
-    // Ensure the directory exists
-    if let Some(parent) = get_data_file_path().parent() {
-        fs::create_dir_all(parent)?;
+    save_data_with(data, SaveOptions::default())
+}
+
+struct SaveOptions {
+    /// Snapshot the previous state into the backups directory
+    backup: bool,
+    /// Commit the data file when the data directory is a git repository
+    commit: bool,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        SaveOptions { backup: true, commit: true }
+    }
+}
+
+fn save_data_with(data: &AppsData, options: SaveOptions) -> Result<()> {
+    // Held so the log and the snapshot move together
+    let _lock = lock_data()?;
+    let store = open_store()?;
+    let config = config::Config::load(&get_config_file_path()?)?;
+    
+    // Snapshot the state we are about to overwrite
+    if options.backup && let Some(previous) = store.snapshot()? {
+        backups::create(&get_backups_dir()?, &previous, config.backups.retention)?;
+    }
+    
+    // The log is the source of truth: append first, then update the snapshot
+    let (previous, _, state) = read_current_data()?;
+    let previous = match state {
+        SnapshotState::External(logged) => logged,
+        _ => previous,
+    };
+    let mut data = data.clone();
+    let (seq, recorded) = record_events(&previous, &data)?;
+    data.event_seq = Some(seq);
+    store.save(&data)?;
+    
+    if options.commit && config.git.enabled {
+        commit_data_file(&recorded);
     }
-    fs::write(get_data_file_path(), content)?;
     Ok(())
 }
 
-fn find_app_by_name<'a>(data: &'a AppsData, search_term: &str) -> Option<&'a App> {
-    let search_lower = search_term.to_lowercase();
+/// Append the events that turn `previous` into `current`. Returns the
+/// sequence number of the last event and the events written.
+fn record_events(previous: &AppsData, current: &AppsData) -> Result<(u64, Vec<events::Event>)> {
+    let log = open_event_log()?;
+    let last_seq = log.last_seq()?;
+    let mut seq = last_seq.unwrap_or(0).max(previous.event_seq.unwrap_or(0));
+    let mut kinds = Vec::new();
+    if last_seq.is_none() && !previous.apps.is_empty() {
+        // First save with history: record what we start from
+        kinds.push(events::EventKind::Snapshot { apps: previous.apps.clone() });
+    }
+    kinds.extend(events::derive(previous, current));
+    
+    let at = Utc::now();
+    let machine = get_machine_name();
+    let new_events: Vec<events::Event> = kinds
+        .into_iter()
+        .map(|kind| {
+            seq += 1;
+            events::Event { seq, at, machine: machine.clone(), kind }
+        })
+        .collect();
+    log.append(&new_events)?;
+    Ok((seq, new_events))
+}
+
+/// Re-read the data under the lock once the user has confirmed a change
+/// planned against `seen`, failing if anything changed in the meantime.
+fn reload_unchanged(seen: &AppsData) -> Result<AppsData> {
+    let current = load_data()?;
+    if current.apps != seen.apps {
+        return Err(anyhow::anyhow!("The data changed while waiting for confirmation; nothing was written. Run the command again."));
+    }
+    Ok(current)
+}
+
+/// Take the data file lock for a read-modify-write cycle. Hold the returned
+/// guard from before `load_data` until after `save_data`.
+fn lock_data() -> Result<storage::DataLock> {
+    storage::DataLock::acquire(&get_lock_file_path()?)
+}
+
+fn migrate_data(dry_run: bool) -> Result<()> {
+    let store = open_store()?;
+    if !store.exists() {
+        println!("No data file found at {}", store.path().display());
+        return Ok(());
+    }
+    
+    let _lock = lock_data()?;
+    let (data, report) = store.load()?;
+    
+    if !report.is_upgrade() {
+        println!("Data file is already at schema version {}.", report.to_version);
+        return Ok(());
+    }
     
-    // First try exact match (case insensitive)
-    for (name, app) in &data.apps {
-        if name.to_lowercase() == search_lower {
-            return Some(app);
+    println!("Schema version: {} -> {}", report.from_version, report.to_version);
+    for step in &report.steps {
+        println!("  v{} -> v{}: {}", step.from, step.to, step.description);
+        if step.changes.is_empty() {
+            println!("    (no changes)");
         }
+        for change in &step.changes {
+            println!("    {}", change);
+        }
+    }
+    
+    if dry_run {
+        println!();
+        println!("Dry run: nothing was written.");
+    } else {
+        save_data(&data)?;
+        println!();
+        println!("✓ Migrated {}", store.path().display());
+    }
+    
+    Ok(())
+}
+
+fn undo_last_change() -> Result<()> {
+    let Some(backup) = backups::list(&get_backups_dir()?)?.pop() else {
+        println!("Nothing to undo: no backups found.");
+        return Ok(());
+    };
+    
+    println!("Undo: restore backup from {}", format_datetime(&backup.taken_at.to_rfc3339()));
+    if apply_backup(&backup, false)? {
+        // The snapshot has been consumed; the next undo steps further back
+        fs::remove_file(&backup.path)?;
+    }
+    Ok(())
+}
+
+fn restore_backup(at: &str) -> Result<()> {
+    let backup = backups::find(&get_backups_dir()?, at)?;
+    println!("Restore backup {} (taken {})", backup.id, format_datetime(&backup.taken_at.to_rfc3339()));
+    apply_backup(&backup, true)?;
+    Ok(())
+}
+
+/// Show what restoring `backup` would change and write it after confirmation.
+/// Returns whether the backup was applied.
+fn apply_backup(backup: &backups::Backup, keep_current_as_backup: bool) -> Result<bool> {
+    let content = fs::read_to_string(&backup.path)?;
+    let (restored, _) = storage::json::parse(&content)?;
+    let current = load_data()?;
+    
+    let changes = diff::diff_data(&current, &restored);
+    if changes.is_empty() {
+        println!("No differences from the current data.");
+        return Ok(true);
+    }
+    
+    println!("Changes:");
+    for change in &changes {
+        println!("  {}", change);
+    }
+    println!();
+    
+    if !confirm("Apply these changes?")? {
+        println!("Restore cancelled.");
+        return Ok(false);
+    }
+    
+    let _lock = lock_data()?;
+    reload_unchanged(&current)?;
+    save_data_with(&restored, SaveOptions { backup: keep_current_as_backup, ..SaveOptions::default() })?;
+    println!("✓ Restored backup {}", backup.id);
+    Ok(true)
+}
+
+fn list_backups() -> Result<()> {
+    let backups = backups::list(&get_backups_dir()?)?;
+    if backups.is_empty() {
+        println!("No backups found.");
+        return Ok(());
+    }
+    
+    println!("Backups (newest last):");
+    for backup in &backups {
+        println!("  {}  {}", backup.id, format_datetime(&backup.taken_at.to_rfc3339()));
+    }
+    Ok(())
+}
+
+fn handle_config_command(command: Option<ConfigCommands>) -> Result<()> {
+    let config_file = get_config_file_path()?;
+    let mut config = config::Config::load(&config_file)?;
+    
+    match command {
+        None | Some(ConfigCommands::Show) => {
+            println!("{}", serde_json::to_string_pretty(&config)?);
+        }
+        Some(ConfigCommands::Get { key }) => {
+            match config.get(&key)? {
+                serde_json::Value::String(value) => println!("{}", value),
+                value => println!("{}", value),
+            }
+        }
+        Some(ConfigCommands::Set { key, value }) => {
+            config.set(&key, &value)?;
+            config.save(&config_file)?;
+            println!("✓ Set {} = {}", key, config.get(&key)?);
+        }
+    }
+    Ok(())
+}
+
+fn bulk_update(filter: &str, action: &BulkAction, dry_run: bool, yes: bool) -> Result<()> {
+    let query = query::Query::parse(filter)?;
+    let data = load_data()?;
+    
+    let mut keys: Vec<String> = data.apps.iter().filter(|(_, app)| query.matches(app)).map(|(key, _)| key.clone()).collect();
+    keys.sort();
+    if keys.is_empty() {
+        println!("No apps match '{}'.", filter);
+        return Ok(());
+    }
+    
+    let mut updated = data.clone();
+    let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    for key in &keys {
+        if let BulkAction::Remove = action {
+            updated.apps.remove(key);
+            continue;
+        }
+        let app = updated.apps.get_mut(key).expect("matched app exists");
+        let before = app.clone();
+        apply_bulk_action(app, action);
+        if *app != before {
+            app.updated_at = now.clone();
+        }
+    }
+    
+    // The timestamps follow from the other changes; leave them out of the plan
+    let changes: Vec<diff::Change> = diff::diff_data(&data, &updated)
+        .into_iter()
+        .filter(|c| !matches!(c, diff::Change::FieldChanged { field: "updated_at", .. }))
+        .collect();
+    println!("{} app(s) match: {}", keys.len(), keys.join(", "));
+    if changes.is_empty() {
+        println!("Nothing to change.");
+        return Ok(());
+    }
+    println!("Changes:");
+    for change in &changes {
+        println!("  {}", change);
+    }
+    
+    if dry_run {
+        println!();
+        println!("Dry run: nothing was written.");
+        return Ok(());
+    }
+    if !yes && !confirm(&format!("Apply {} change(s)?", changes.len()))? {
+        println!("Bulk update cancelled.");
+        return Ok(());
+    }
+    
+    let _lock = lock_data()?;
+    reload_unchanged(&data)?;
+    save_data(&updated)?;
+    println!("✓ Applied {} change(s)", changes.len());
+    Ok(())
+}
+
+fn apply_bulk_action(app: &mut App, action: &BulkAction) {
+    match action {
+        BulkAction::Tag { tag_command: BulkTagCommands::Add { tags } } => {
+            for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+                if !app.tags.iter().any(|t| t == tag) {
+                    app.tags.push(tag.to_string());
+                }
+            }
+        }
+        BulkAction::Tag { tag_command: BulkTagCommands::Remove { tags } } => {
+            app.tags.retain(|t| !tags.iter().any(|r| r.trim() == t));
+        }
+        BulkAction::Set { field: BulkField::GithubRepo, value } => {
+            app.github_repo = value.clone();
+        }
+        BulkAction::Set { field: BulkField::Notes, value } => {
+            if let Some(profile) = app.profiles.iter_mut().find(|p| p.active) {
+                profile.notes = value.clone();
+            }
+        }
+        BulkAction::AddTask { task } => {
+            app.tasks.push(task.clone());
+        }
+        // Whole apps are removed by the caller
+        BulkAction::Remove => {}
+    }
+}
+
+fn handle_view_command(name: Option<&str>, command: Option<ViewCommands>) -> Result<()> {
+    let views_file = get_views_file_path()?;
+    // Changes read and write the views under the data lock so concurrent
+    // saves don't drop each other's views
+    let _lock = match command {
+        Some(ViewCommands::Save { .. } | ViewCommands::Rm { .. }) => Some(lock_data()?),
+        _ => None,
+    };
+    let mut views = views::Views::load(&views_file)?;
+    
+    match (name, command) {
+        (Some(name), _) => {
+            let view = views.views.get(name).ok_or_else(|| anyhow::anyhow!("No view named '{}'. See `apps-helper view list`.", name))?;
+            run_view(view)?;
+        }
+        (None, Some(ViewCommands::Save { name, r#where, sort, format, limit })) => {
+            if views::RESERVED_NAMES.contains(&name.as_str()) {
+                return Err(anyhow::anyhow!("'{}' is a view subcommand; pick another name", name));
+            }
+            if let Some(filter) = &r#where {
+                query::Query::parse(filter)?;
+            }
+            let view = views::View { r#where, sort, format, limit };
+            let replaced = views.views.insert(name.clone(), view).is_some();
+            views.save(&views_file)?;
+            println!("✓ {} view {}", if replaced { "Updated" } else { "Saved" }, name);
+        }
+        (None, Some(ViewCommands::List)) => {
+            if views.views.is_empty() {
+                println!("No saved views.");
+            }
+            let width = views.views.keys().map(|n| n.len()).max().unwrap_or(0);
+            for (name, view) in &views.views {
+                println!("{:<width$}  {}", name, describe_view(view), width = width);
+            }
+        }
+        (None, Some(ViewCommands::Rm { name })) => {
+            if views.views.remove(&name).is_none() {
+                return Err(anyhow::anyhow!("No view named '{}'", name));
+            }
+            views.save(&views_file)?;
+            println!("✓ Removed view {}", name);
+        }
+        (None, None) => {
+            return Err(anyhow::anyhow!("Please provide a view name or a subcommand (save, list, rm)"));
+        }
+    }
+    Ok(())
+}
+
+fn run_view(view: &views::View) -> Result<()> {
+    let data = load_data()?;
+    let mut apps = filter_apps(&data, view.r#where.as_deref())?;
+    match view.sort.unwrap_or(views::SortKey::Name) {
+        views::SortKey::Name => apps.sort_by(|a, b| a.name.cmp(&b.name)),
+        views::SortKey::Updated => apps.sort_by(|a, b| b.updated_at.cmp(&a.updated_at)),
+        views::SortKey::Created => apps.sort_by(|a, b| b.created_at.cmp(&a.created_at)),
+    }
+    if let Some(limit) = view.limit {
+        apps.truncate(limit);
     }
     
-    // Then try fuzzy matching: remove spaces, hyphens, underscores
-    let normalized_search = normalize_name(&search_lower);
+    if apps.is_empty() {
+        println!("No apps found.");
+        return Ok(());
+    }
+    match view.format {
+        views::ViewFormat::Full => apps.into_iter().for_each(print_app_summary),
+        views::ViewFormat::Oneline => print_apps_oneline(&apps),
+    }
+    Ok(())
+}
+
+/// The options a view was saved with, as they'd be passed to `view save`.
+fn describe_view(view: &views::View) -> String {
+    let mut parts = Vec::new();
+    if let Some(filter) = &view.r#where {
+        parts.push(format!("--where '{}'", filter));
+    }
+    if let Some(sort) = view.sort {
+        parts.push(format!("--sort {}", sort.to_possible_value().map_or_else(String::new, |v| v.get_name().to_string())));
+    }
+    if view.format != views::ViewFormat::Full {
+        parts.push("--format oneline".to_string());
+    }
+    if let Some(limit) = view.limit {
+        parts.push(format!("--limit {}", limit));
+    }
+    parts.join(" ")
+}
+
+fn show_log(app: Option<&str>, limit: Option<usize>) -> Result<()> {
+    let log = open_event_log()?;
+    let mut events = log.read_all()?;
+    
+    if let Some(term) = app {
+        let data = load_data()?;
+        let name = find_app_by_name(&data, term)?.map(|a| a.name.clone()).unwrap_or_else(|| term.to_string());
+        events.retain(|e| e.kind.touches(&name));
+    }
+    if let Some(limit) = limit {
+        let skip = events.len().saturating_sub(limit);
+        events.drain(..skip);
+    }
+    
+    if events.is_empty() {
+        println!("No events found.");
+        return Ok(());
+    }
+    
+    for event in &events {
+        let machine = event.machine.as_deref().map(|m| format!(" [{}]", m)).unwrap_or_default();
+        println!("{:>5}  {}{}  {}", event.seq, format_datetime(&event.at.to_rfc3339()), machine, event.kind.describe());
+    }
+    Ok(())
+}
+
+fn show_data_at(at: &str, app: Option<&str>) -> Result<()> {
+    let at_time = parse_timestamp(at).ok_or_else(|| anyhow::anyhow!("Invalid timestamp '{}'. Use RFC 3339 or YYYY-MM-DD[ HH:MM:SS]", at))?;
+    let events = open_event_log()?.read_all()?;
     
-    for (name, app) in &data.apps {
-        let normalized_name = normalize_name(&name.to_lowercase());
-        if normalized_name == normalized_search {
-            return Some(app);
+    match events.first() {
+        None => return Err(anyhow::anyhow!("No history recorded yet")),
+        Some(first) if first.at > at_time => {
+            return Err(anyhow::anyhow!("History starts at {}", format_datetime(&first.at.to_rfc3339())));
         }
+        Some(_) => {}
     }
     
-    // Finally try contains match
-    for (name, app) in &data.apps {
-        let normalized_name = normalize_name(&name.to_lowercase());
-        if normalized_name.contains(&normalized_search) || normalized_search.contains(&normalized_name) {
-            return Some(app);
+    let data = events::replay(events.iter().take_while(|e| e.at <= at_time));
+    println!("As of {}:", format_datetime(&at_time.to_rfc3339()));
+    
+    let mut apps: Vec<&App> = match app {
+        Some(term) => find_app_by_name(&data, term)?.into_iter().collect(),
+        None => data.apps.values().collect(),
+    };
+    apps.sort_by(|a, b| a.name.cmp(&b.name));
+    if apps.is_empty() {
+        println!("  No apps found.");
+    }
+    for app in apps {
+        println!("  {}", app.name);
+        if let Some(active_profile) = app.profiles.iter().find(|p| p.active) {
+            println!("    {:?}: {} (active)", active_profile.profile_type, active_profile.location.display());
+        }
+        if !app.tags.is_empty() {
+            println!("    Tags: {}", app.tags.join(", "));
         }
+        if !app.tasks.is_empty() {
+            println!("    Tasks: {} task(s)", app.tasks.len());
+        }
+    }
+    Ok(())
+}
+
+fn rebuild_from_log(yes: bool) -> Result<()> {
+    // Loading first lets it log any outside edits before we replay
+    let current = load_data()?;
+    let events = open_event_log()?.read_all()?;
+    if events.is_empty() {
+        return Err(anyhow::anyhow!("No history recorded yet; nothing to rebuild from"));
+    }
+    
+    let rebuilt = events::replay(&events);
+    let changes = diff::diff_data(&current, &rebuilt);
+    if changes.is_empty() {
+        println!("Data file already matches the event log ({} events).", events.len());
+        return Ok(());
+    }
+    println!("Differences between the data file and the event log:");
+    for change in &changes {
+        println!("  {}", change);
+    }
+    println!();
+    if !yes && !confirm("Replace the data file with the state from the log?")? {
+        println!("Rebuild cancelled.");
+        return Ok(());
     }
     
-    None
+    let _lock = lock_data()?;
+    reload_unchanged(&current)?;
+    save_data(&rebuilt)?;
+    println!("✓ Rebuilt data file from {} events", events.len());
+    Ok(())
 }
 
-fn find_app_by_name_mut<'a>(data: &'a mut AppsData, search_term: &str) -> Option<&'a mut App> {
-    let search_lower = search_term.to_lowercase();
+fn merge_data_file(other_path: &std::path::Path, dry_run: bool) -> Result<()> {
+    let other_store = storage::open(other_path);
+    if !other_store.exists() {
+        return Err(anyhow::anyhow!("File not found: {}", other_path.display()));
+    }
+    let (other, _) = other_store.load()?;
     
-    // Collect keys to avoid borrow checker issues
-    let keys: Vec<String> = data.apps.keys().cloned().collect();
+    let _lock = lock_data()?;
+    let local = load_data()?;
+    let outcome = merge::merge(&local, &other);
     
-    // Try exact match first
-    for name in &keys {
-        if name.to_lowercase() == search_lower {
-            return data.apps.get_mut(name);
+    let changes = diff::diff_data(&local, &outcome.data);
+    if changes.is_empty() {
+        println!("Nothing to merge: {} adds no changes.", other_path.display());
+    } else {
+        println!("Changes:");
+        for change in &changes {
+            println!("  {}", change);
         }
     }
     
-    // Then try fuzzy matching
-    let normalized_search = normalize_name(&search_lower);
-    for name in &keys {
-        let normalized_name = normalize_name(&name.to_lowercase());
-        if normalized_name == normalized_search {
-            return data.apps.get_mut(name);
+    if !outcome.conflicts.is_empty() {
+        println!();
+        println!("Conflicts (resolved by most recent update):");
+        for conflict in &outcome.conflicts {
+            println!("  {}", conflict);
         }
     }
     
-    // Finally try contains match
-    for name in &keys {
-        let normalized_name = normalize_name(&name.to_lowercase());
-        if normalized_name.contains(&normalized_search) || normalized_search.contains(&normalized_name) {
-            return data.apps.get_mut(name);
+    check_new_collisions(&local, &outcome.data)?;
+    if dry_run {
+        println!();
+        println!("Dry run: nothing was written.");
+    } else if !changes.is_empty() {
+        save_data(&outcome.data)?;
+        println!();
+        println!("✓ Merged {}", other_path.display());
+    }
+    Ok(())
+}
+
+fn export_data(format: Option<transfer::Format>, output: Option<&std::path::Path>) -> Result<()> {
+    let format = format
+        .or_else(|| output.and_then(transfer::Format::for_path))
+        .unwrap_or(transfer::Format::Json);
+    let data = load_data()?;
+    let content = transfer::export(&data, format)?;
+    
+    match output {
+        Some(path) => {
+            fs::write(path, content).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+            eprintln!("✓ Exported {} app(s) to {}", data.apps.len(), path.display());
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+fn generate_workspace(filter: Option<&str>, output: Option<&std::path::Path>) -> Result<()> {
+    let data = load_data()?;
+    let mut apps = filter_apps(&data, filter)?;
+    apps.sort_by(|a, b| a.name.cmp(&b.name));
+    if apps.is_empty() {
+        return Err(anyhow::anyhow!("No apps match"));
+    }
+
+    let mut folders = Vec::new();
+    for app in apps {
+        if !app.profiles.iter().any(|p| p.profile_type == ProfileType::Dev) {
+            eprintln!("Skipping {}: no Dev profile", app.name);
+            continue;
         }
+        match profile_location(app, Some(ProfileType::Dev)) {
+            Ok(location) => folders.push(workspace::Folder { name: app.name.clone(), path: locate::canonical(&location) }),
+            Err(e) => eprintln!("Skipping {}: {:#}", app.name, e),
+        }
+    }
+    if folders.is_empty() {
+        return Err(anyhow::anyhow!("No matching app has a Dev profile"));
     }
+
+    let Some(path) = output else {
+        let content = workspace::generate(None, &folders, &std::env::current_dir()?)?;
+        print!("{}", content);
+        return Ok(());
+    };
+    let existing = match fs::read_to_string(path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(anyhow::anyhow!("Failed to read {}: {}", path.display(), e)),
+    };
+    let base = locate::canonical(path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(std::path::Path::new(".")));
+    let content = workspace::generate(existing.as_deref(), &folders, &base)
+        .map_err(|e| anyhow::anyhow!("Invalid workspace file {}: {:#}", path.display(), e))?;
+    if existing.as_deref() == Some(content.as_str()) {
+        eprintln!("✓ {} is up to date ({} folder(s))", path.display(), folders.len());
+    } else {
+        storage::write_atomic(path, content.as_bytes())?;
+        eprintln!("✓ Wrote {} folder(s) to {}", folders.len(), path.display());
+    }
+    Ok(())
+}
+
+fn import_data(path: &std::path::Path, format: Option<transfer::Format>, merge: bool, dry_run: bool, yes: bool) -> Result<()> {
+    let from_stdin = path.as_os_str() == "-";
+    let format = format
+        .or_else(|| if from_stdin { None } else { transfer::Format::for_path(path) })
+        .ok_or_else(|| anyhow::anyhow!("Cannot tell the format of {}; pass --format", path.display()))?;
+    let content = if from_stdin {
+        io::read_to_string(io::stdin())?
+    } else {
+        fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?
+    };
+    let imported = transfer::import(&content, format)?;
+    let count = imported.apps.len();
     
-    None
+    let local = load_data()?;
+    let (mut result, conflicts) = if merge {
+        let outcome = merge::merge(&local, &imported);
+        (outcome.data, outcome.conflicts)
+    } else {
+        (imported, Vec::new())
+    };
+    result.schema_version = local.schema_version;
+    result.event_seq = local.event_seq;
+    
+    let changes = diff::diff_data(&local, &result);
+    if changes.is_empty() {
+        println!("Nothing to import: {} adds no changes.", path.display());
+        return Ok(());
+    }
+    println!("Changes:");
+    for change in &changes {
+        println!("  {}", change);
+    }
+    if !conflicts.is_empty() {
+        println!();
+        println!("Conflicts (resolved by most recent update):");
+        for conflict in &conflicts {
+            println!("  {}", conflict);
+        }
+    }
+    
+    check_new_collisions(&local, &result)?;
+    if dry_run {
+        println!();
+        println!("Dry run: nothing was written.");
+        return Ok(());
+    }
+    let removed = changes.iter().filter(|c| matches!(c, diff::Change::AppRemoved(_))).count();
+    if removed > 0 && !yes && !confirm(&format!("Replacing removes {} app(s). Continue?", removed))? {
+        println!("Import cancelled.");
+        return Ok(());
+    }
+    
+    let _lock = lock_data()?;
+    reload_unchanged(&local)?;
+    save_data(&result)?;
+    println!();
+    println!("✓ Imported {} app(s) from {}", count, path.display());
+    Ok(())
 }
 
-fn find_app_by_current_dir(data: &AppsData) -> Result<Option<&App>> {
-    let current_dir = std::env::current_dir()?;
+/// Returns whether the file is valid.
+fn validate_data_file(file: Option<PathBuf>) -> Result<bool> {
+    let path = match file {
+        Some(path) => path,
+        None => get_data_file_path()?,
+    };
+    let store = storage::open(&path);
+    if !store.exists() {
+        return Err(anyhow::anyhow!("File not found: {}", path.display()));
+    }
     
-    for (_, app) in &data.apps {
-        // Check profiles first
-        for profile in &app.profiles {
-            if profile.location == current_dir {
-                return Ok(Some(app));
+    let mut document = match storage::Backend::for_path(&path) {
+        storage::Backend::Json => {
+            let content = fs::read_to_string(&path)?;
+            match serde_json::from_str::<serde_json::Value>(&content) {
+                Ok(document) => document,
+                Err(e) => {
+                    println!("✗ {} is not valid JSON: {}", path.display(), e);
+                    return Ok(false);
+                }
             }
         }
+        // The database enforces its own structure; check what it holds
+        storage::Backend::Sqlite => serde_json::to_value(store.load()?.0)?,
+    };
+    let report = match migrations::migrate(&mut document) {
+        Ok(report) => report,
+        Err(e) => {
+            println!("✗ {}: {}", path.display(), e);
+            return Ok(false);
+        }
+    };
+    
+    let problems = validate::check_document(&document);
+    if problems.is_empty() {
+        println!("✓ {} is valid ({} app(s))", path.display(), document["apps"].as_object().map_or(0, |a| a.len()));
+        if report.is_upgrade() {
+            println!("It uses schema version {}; run `apps-helper migrate` to upgrade it.", report.from_version);
+        }
+        return Ok(true);
+    }
+    println!("✗ {} has {} problem(s):", path.display(), problems.len());
+    for problem in &problems {
+        println!("  {}", problem);
+    }
+    Ok(false)
+}
+
+fn handle_storage_command(command: StorageCommands) -> Result<()> {
+    match command {
+        StorageCommands::Info => {
+            let data_file = get_data_file_path()?;
+            println!("Backend: {:?}", storage::Backend::for_path(&data_file));
+            println!("Data file: {}", data_file.display());
+        }
+        StorageCommands::Migrate { to, force } => {
+            migrate_storage(to, force)?;
+        }
+    }
+    Ok(())
+}
+
+fn migrate_storage(to: storage::Backend, force: bool) -> Result<()> {
+    let _lock = lock_data()?;
+    let source = open_store()?;
+    let from = storage::Backend::for_path(source.path());
+    if from == to {
+        println!("Already using the {:?} backend ({}).", to, source.path().display());
+        return Ok(());
+    }
+    if to != storage::Backend::Json && config::Config::load(&get_config_file_path()?)?.git.enabled {
+        return Err(anyhow::anyhow!("Git sync needs the JSON backend; set git.enabled to false first"));
+    }
+    
+    let data = if source.exists() { source.load()?.0 } else { AppsData::default() };
+    let target_path = storage::sibling_path(source.path(), to);
+    let target = storage::open(&target_path);
+    if target.exists() {
+        if !force {
+            return Err(anyhow::anyhow!("{} already exists. Pass --force to replace it.", target_path.display()));
+        }
+        let kept = retire_data_file(&target_path)?;
+        println!("Moved the existing {} to {}", target_path.display(), kept.display());
+    }
+    
+    target.save(&data)?;
+    
+    // Read it back and compare before switching over
+    let (copied, _) = target.load()?;
+    if serde_json::to_value(&copied)? != serde_json::to_value(&data)? {
+        fs::remove_file(&target_path)?;
+        return Err(anyhow::anyhow!("Verification failed: data read back from {} differs from the source", target_path.display()));
+    }
+    
+    println!("✓ Copied {} app(s) from {} to {}", data.apps.len(), source.path().display(), target_path.display());
+    
+    if DATA_FILE_OVERRIDE.get().is_some() {
+        println!("Pass --data-file {} to use the new backend.", target_path.display());
+    } else {
+        let config_file = get_config_file_path()?;
+        let mut config = config::Config::load(&config_file)?;
+        config.storage.backend = to;
+        config.save(&config_file)?;
+        println!("✓ Switched storage backend to {:?}", to);
+    }
+    if source.exists() {
+        // Out of the way, so migrating back later starts clean
+        let kept = retire_data_file(source.path())?;
+        println!("The old file is kept as {}", kept.display());
+    }
+    Ok(())
+}
+
+/// Move a data file that is being replaced as a whole into the backups
+/// directory, under a timestamped name `restore` doesn't list.
+fn retire_data_file(path: &std::path::Path) -> Result<PathBuf> {
+    let backups_dir = get_backups_dir()?;
+    fs::create_dir_all(&backups_dir)?;
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or(paths::DATA_FILE_NAME);
+    let target = backups_dir.join(format!("replaced-{}-{}", Utc::now().format("%Y%m%dT%H%M%S%.3fZ"), file_name));
+    fs::rename(path, &target).map_err(|e| anyhow::anyhow!("Failed to move {} to {}: {}", path.display(), target.display(), e))?;
+    Ok(target)
+}
+
+fn data_file_name() -> Result<String> {
+    let data_file = get_data_file_path()?;
+    data_file
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(String::from)
+        .ok_or_else(|| anyhow::anyhow!("Invalid data file path: {}", data_file.display()))
+}
+
+/// Commit the data file after a save, describing the change by its events.
+/// Failing to commit doesn't undo the save, so it only warns.
+fn commit_data_file(recorded: &[events::Event]) {
+    let message = match recorded {
+        [] => "update apps".to_string(),
+        [event] => event.kind.describe(),
+        [event, rest @ ..] => format!("{} (+{} more)", event.kind.describe(), rest.len()),
+    };
+    let result = get_data_dir().and_then(|dir| {
+        let repo = git::Repo::new(&dir);
+        if !repo.is_repo() {
+            return Err(anyhow::anyhow!("{} is not a git repository; run `apps-helper git init`", dir.display()));
+        }
+        repo.commit(&[&data_file_name()?], &message)
+    });
+    if let Err(e) = result {
+        eprintln!("Warning: saved, but could not commit the change: {:#}", e);
+    }
+}
+
+fn git_init(remote: Option<&str>) -> Result<()> {
+    let _lock = lock_data()?;
+    let data_file = get_data_file_path()?;
+    if storage::Backend::for_path(&data_file) != storage::Backend::Json {
+        return Err(anyhow::anyhow!("Git sync needs the JSON backend. Run `apps-helper storage migrate --to json` first."));
+    }
+    
+    let data_dir = get_data_dir()?;
+    fs::create_dir_all(&data_dir)?;
+    let repo = git::Repo::new(&data_dir);
+    if !repo.is_repo() {
+        repo.init()?;
+        println!("✓ Initialized git repository in {}", data_dir.display());
+    }
+    
+    // Only the registry itself is shared; backups, the event log and the
+    // config stay local to each machine
+    let file_name = data_file_name()?;
+    let gitignore = data_dir.join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(&gitignore, format!("*\n!.gitignore\n!{}\n", file_name))?;
+    }
+    
+    // Write the data file in its canonical form so the first commit is clean
+    let data = load_data()?;
+    open_store()?.save(&data)?;
+    repo.commit(&[".gitignore", &file_name], "track apps-helper data")?;
+    
+    let config_file = get_config_file_path()?;
+    let mut config = config::Config::load(&config_file)?;
+    if let Some(url) = remote {
+        if repo.has_remote(&config.git.remote)? {
+            return Err(anyhow::anyhow!("Remote '{}' already exists in {}", config.git.remote, data_dir.display()));
+        }
+        repo.add_remote(&config.git.remote, url)?;
+        println!("✓ Added remote {} ({})", config.git.remote, url);
+    }
+    config.git.enabled = true;
+    config.save(&config_file)?;
+    println!("✓ Every change will now be committed. Run `apps-helper sync` to pull and push.");
+    Ok(())
+}
+
+fn sync_data() -> Result<()> {
+    let config = config::Config::load(&get_config_file_path()?)?;
+    let data_dir = get_data_dir()?;
+    let repo = git::Repo::new(&data_dir);
+    if !config.git.enabled || !repo.is_repo() {
+        return Err(anyhow::anyhow!("Git sync is not set up. Run `apps-helper git init --remote <url>` first."));
+    }
+    let remote = config.git.remote.as_str();
+    if !repo.has_remote(remote)? {
+        return Err(anyhow::anyhow!("No remote named '{}' in {}. Add one with `git -C {} remote add {} <url>`.", remote, data_dir.display(), data_dir.display(), remote));
+    }
+    
+    let _lock = lock_data()?;
+    let file_name = data_file_name()?;
+    
+    // Pick up anything edited by hand and commit what's pending
+    load_data()?;
+    repo.commit(&[&file_name], "update apps")?;
+    
+    let branch = repo.current_branch()?;
+    if !repo.fetch(remote, &branch)? {
+        repo.push(remote, &branch)?;
+        println!("✓ Pushed {} to {} (new branch)", branch, remote);
+        return Ok(());
+    }
+    
+    if repo.is_ancestor("FETCH_HEAD", "HEAD")? {
+        repo.push(remote, &branch)?;
+        println!("✓ Up to date with {}; pushed local changes", remote);
+        return Ok(());
+    }
+    
+    let local = load_data()?;
+    if repo.is_ancestor("HEAD", "FETCH_HEAD")? {
+        repo.merge_ff_only("FETCH_HEAD")?;
+    } else {
+        // Both sides changed: merge the registries app by app instead of
+        // letting git merge the JSON text
+        let theirs = match repo.show("FETCH_HEAD", &file_name)? {
+            Some(content) => storage::json::parse(&content)?.0,
+            None => AppsData::default(),
+        };
+        let outcome = merge::merge(&local, &theirs);
+        check_new_collisions(&local, &outcome.data)?;
+        let log = open_event_log()?;
+        let log_size = log.size()?;
+        repo.merge_ours("FETCH_HEAD")?;
+        let merged = save_data_with(&outcome.data, SaveOptions { commit: false, ..SaveOptions::default() })
+            .and_then(|_| repo.commit_merge(&[&file_name], &format!("sync: merge {}/{}", remote, branch)));
+        if let Err(e) = merged {
+            // Put the data file, the log and the snapshot's place in it
+            // back as they were before the merge
+            repo.abort_merge()?;
+            log.truncate(log_size)?;
+            open_store()?.save(&local)?;
+            return Err(e);
+        }
         
-        // Check legacy directory field
-        if let Some(app_dir) = &app.directory {
-            if app_dir == &current_dir {
-                return Ok(Some(app));
+        if !outcome.conflicts.is_empty() {
+            println!("Conflicts (resolved by most recent update):");
+            for conflict in &outcome.conflicts {
+                println!("  {}", conflict);
+            }
+        }
+    }
+    
+    // Record what came in from the remote in the local event log
+    let current = load_data()?;
+    let changes = diff::diff_data(&local, &current);
+    if !changes.is_empty() {
+        println!("Changes from {}:", remote);
+        for change in &changes {
+            println!("  {}", change);
+        }
+    }
+    
+    repo.push(remote, &branch)?;
+    println!("✓ Synced with {}", remote);
+    Ok(())
+}
+
+fn find_app_by_name<'a>(data: &'a AppsData, search_term: &str) -> Result<Option<&'a App>> {
+    Ok(resolve_app_key(data, search_term)?.and_then(|key| data.apps.get(&key)))
+}
+
+/// Like find_app_by_name, but only an exact name or alias counts, so a
+/// destructive command never acts on a fuzzy guess.
+fn find_app_by_exact_name<'a>(data: &'a AppsData, search_term: &str) -> Result<Option<&'a App>> {
+    match matcher::resolve_exact(data, search_term) {
+        matcher::Resolution::Found(key) => Ok(data.apps.get(key)),
+        matcher::Resolution::Ambiguous(matches) => {
+            let names: Vec<&str> = matches.iter().map(|m| m.key).collect();
+            Err(anyhow::anyhow!("'{}' is an alias of several apps: {}. Use the app's name.", search_term, names.join(", ")))
+        }
+        matcher::Resolution::NotFound => match matcher::rank(data, search_term).first() {
+            Some(best) => Err(anyhow::anyhow!("No app is named '{}'; did you mean '{}'? Use the exact name or an alias.", search_term, best.key)),
+            None => Ok(None),
+        },
+    }
+}
+
+/// Key of the app `search_term` refers to, or `search_term` itself when
+/// nothing matches so the caller reports it as not found. Commands that
+/// change an app call this before taking the lock: resolving may open the
+/// picker, and the lock mustn't wait on the user.
+fn resolve_before_lock(search_term: &str) -> Result<String> {
+    let data = load_data()?;
+    Ok(resolve_app_key(&data, search_term)?.unwrap_or_else(|| search_term.to_string()))
+}
+
+/// Key of the app `search_term` refers to. When several apps match about
+/// equally well, ask which one on a terminal and refuse otherwise.
+fn resolve_app_key(data: &AppsData, search_term: &str) -> Result<Option<String>> {
+    let matches = match matcher::resolve(data, search_term) {
+        matcher::Resolution::Found(key) => return Ok(Some(key.to_string())),
+        matcher::Resolution::NotFound => return Ok(None),
+        matcher::Resolution::Ambiguous(matches) => matches,
+    };
+    
+    let names: Vec<&str> = matches.iter().map(|m| m.key).collect();
+    if !(io::stdin().is_terminal() && io::stderr().is_terminal()) {
+        return Err(anyhow::anyhow!("'{}' matches several apps: {}. Be more specific.", search_term, names.join(", ")));
+    }
+    
+    // Let the user pick among the close matches, starting from what they typed
+    match pick::pick(data, &names, search_term)? {
+        Some(key) => Ok(Some(key)),
+        None => Err(anyhow::anyhow!("No app selected")),
+    }
+}
+
+/// Returns whether an app was chosen.
+fn pick_app(query: &str, print_path: bool, profile_type: Option<ProfileType>) -> Result<bool> {
+    if !(io::stdin().is_terminal() && io::stderr().is_terminal()) {
+        return Err(anyhow::anyhow!("pick needs a terminal"));
+    }
+    let data = load_data()?;
+    let mut keys: Vec<&str> = data.apps.keys().map(String::as_str).collect();
+    keys.sort();
+    if keys.is_empty() {
+        return Err(anyhow::anyhow!("No apps registered"));
+    }
+
+    let Some(key) = pick::pick(&data, &keys, query)? else {
+        return Ok(false);
+    };
+    let app = &data.apps[&key];
+    if print_path {
+        println!("{}", profile_location(app, profile_type)?.display());
+    } else {
+        println!("{}", app.name);
+    }
+    Ok(true)
+}
+
+fn search_apps(query: &str, regex: bool) -> Result<()> {
+    let data = load_data()?;
+    let pattern = search::pattern(query, regex)?;
+    // Names are also matched by spelling, as with --get
+    let results = search::search(&data, &pattern, (!regex).then_some(query));
+    if results.is_empty() {
+        println!("No apps match '{}'.", query);
+        return Ok(());
+    }
+    
+    let highlight = io::stdout().is_terminal();
+    for (i, found) in results.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}  ({})", found.key, found.score);
+        for hit in &found.hits {
+            println!("  {}: {}", hit.field, hit.snippet(highlight));
+        }
+    }
+    Ok(())
+}
+
+fn open_app(search_term: &str, profile_type: Option<ProfileType>, with: Option<Opener>) -> Result<()> {
+    let data = load_data()?;
+    let app = find_app_by_name(&data, search_term)?.ok_or_else(|| anyhow::anyhow!("App '{}' not found", search_term))?;
+    let location = profile_location(app, profile_type)?;
+    if !location.is_dir() {
+        return Err(anyhow::anyhow!("{} does not exist on this machine", location.display()));
+    }
+    
+    let config = config::Config::load(&get_config_file_path()?)?;
+    let opener = with.or(app.opener).unwrap_or(config.open.default);
+    let args = launch::command_line(config.open.template(opener), &location, &app.name, &launch::editor())?;
+    launch::run(&args, &location)
+        .map_err(|e| anyhow::anyhow!("{:#}. Change the command with `apps-helper config set open.{} <template>`.", e, format!("{:?}", opener).to_lowercase()))
+}
+
+fn tmux_session(search_term: &str, detach: bool) -> Result<()> {
+    let data = load_data()?;
+    let app = find_app_by_name(&data, search_term)?.ok_or_else(|| anyhow::anyhow!("App '{}' not found", search_term))?;
+    let session = tmux::session_name(&app.name);
+
+    if !tmux::has_session(&session)? {
+        // Apps without a Dev profile start in their active one
+        let has_dev = app.profiles.iter().any(|p| p.profile_type == ProfileType::Dev);
+        let start = profile_location(app, has_dev.then_some(ProfileType::Dev))?;
+        let editor = launch::editor();
+        let mut windows = Vec::new();
+        for window in &app.layout {
+            let mut panes = Vec::new();
+            for pane in &window.panes {
+                let dir = match pane.profile_type {
+                    Some(profile_type) => profile_location(app, Some(profile_type))?,
+                    None => start.clone(),
+                };
+                let command = pane.command.as_deref().map(|c| tmux::pane_command(c, &dir, &app.name, &editor));
+                panes.push((dir, command));
             }
+            windows.push(tmux::Planned { name: window.name.clone(), panes });
+        }
+        for dir in std::iter::once(&start).chain(windows.iter().flat_map(|w| w.panes.iter().map(|(dir, _)| dir))) {
+            if !dir.is_dir() {
+                return Err(anyhow::anyhow!("{} does not exist on this machine", dir.display()));
+            }
+        }
+        tmux::create(&session, &start, &windows)?;
+        println!("✓ Created tmux session {}", session);
+    }
+    if !detach {
+        tmux::attach(&session)?;
+    }
+    Ok(())
+}
+
+/// Location of the app's profile of `profile_type`, or of its active profile.
+fn resolve_profile_location(search_term: &str, profile_type: Option<ProfileType>) -> Result<PathBuf> {
+    let data = load_data()?;
+    let app = find_app_by_name(&data, search_term)?.ok_or_else(|| anyhow::anyhow!("App '{}' not found", search_term))?;
+    profile_location(app, profile_type)
+}
+
+fn profile_location(app: &App, profile_type: Option<ProfileType>) -> Result<PathBuf> {
+    let profile = match profile_type {
+        Some(profile_type) => &app.profiles[find_profile_index(app, profile_type, None)?],
+        None => app
+            .profiles
+            .iter()
+            .find(|p| p.active)
+            .ok_or_else(|| anyhow::anyhow!("{} has no active profile; pass --type", app.name))?,
+    };
+    Ok(profile.location.clone())
+}
+
+fn find_app_by_current_dir(data: &AppsData) -> Result<Option<&App>> {
+    let current_dir = std::env::current_dir()?;
+    let machine = get_machine_name();
+    Ok(locate::find_by_dir(data, &current_dir, machine.as_deref()).map(|found| &data.apps[found.key]))
+}
+
+/// Returns whether the current directory belongs to an app.
+fn show_here(quiet: bool) -> Result<bool> {
+    let data = load_data()?;
+    let current_dir = std::env::current_dir()?;
+    let machine = get_machine_name();
+    let Some(found) = locate::find_by_dir(&data, &current_dir, machine.as_deref()) else {
+        if !quiet {
+            eprintln!("{} is not inside any registered app.", current_dir.display());
         }
+        return Ok(false);
+    };
+    if quiet {
+        return Ok(true);
     }
     
-    Ok(None)
+    let app = &data.apps[found.key];
+    let profile = found.profile;
+    println!("{}", app.name);
+    let active_marker = if profile.active { " (active)" } else { "" };
+    println!("  Inside: {:?} profile at {}{}", profile.profile_type, profile.location.display(), active_marker);
+    if let Some(ref machine) = profile.machine_name {
+        println!("    Machine: {}", machine);
+    }
+    if let Some(branch) = git::Repo::new(&current_dir).head_name() {
+        println!("  Branch: {}", branch);
+    }
+    if !app.tags.is_empty() {
+        println!("  Tags: {}", app.tags.join(", "));
+    }
+    if let Some(ref repo) = app.github_repo {
+        println!("  GitHub: {}", repo);
+    }
+    if app.tasks.is_empty() {
+        println!("  No open tasks");
+    } else {
+        println!("  Tasks:");
+        for (i, task) in app.tasks.iter().enumerate() {
+            println!("    {}. {}", i + 1, task);
+        }
+    }
+    println!("  Updated: {}", format_datetime(&app.updated_at));
+    Ok(true)
+}
+
+/// Print the prompt segment from the path index, rebuilding it only when
+/// the data file has changed. Returns whether the directory belongs to an app.
+fn show_prompt(format: &str, shell: prompt::Shell) -> Result<bool> {
+    let format = prompt::Format::parse(format)?;
+    let data_file = get_data_file_path()?;
+    let index_file = get_prompt_index_path()?;
+    
+    let index = match prompt::Index::load_fresh(&index_file, prompt::Stamp::of(&data_file)) {
+        Some(index) => index,
+        None => {
+            let data = load_data()?;
+            let machine = get_machine_name();
+            // Loading may have upgraded the file; stamp what is there now
+            let index = prompt::Index::build(&data, prompt::Stamp::of(&data_file), machine.as_deref());
+            index.save(&index_file)?;
+            index
+        }
+    };
+    
+    match index.find(&std::env::current_dir()?) {
+        Some(entry) => {
+            println!("{}", format.render(entry, shell));
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Key of the app the current directory belongs to, for `app --current-dir`.
+fn current_dir_app_key() -> Result<String> {
+    let data = load_data()?;
+    let current_dir = std::env::current_dir()?;
+    let machine = get_machine_name();
+    locate::find_by_dir(&data, &current_dir, machine.as_deref())
+        .map(|found| found.key.to_string())
+        .ok_or_else(|| anyhow::anyhow!("No app found for current directory {}", current_dir.display()))
+}
+
+fn confirm(prompt: &str) -> Result<bool> {
+    print!("{} (y/N): ", prompt);
+    io::stdout().flush()?;
+    
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let input = input.trim().to_lowercase();
+    Ok(input == "y" || input == "yes")
 }
 
 fn normalize_name(name: &str) -> String {
@@ -811,6 +2786,23 @@ fn format_datetime(datetime_str: &str) -> String {
     }
 }
 
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(value, format) {
+            return chrono::Local.from_local_datetime(&dt).single().map(|dt| dt.with_timezone(&Utc));
+        }
+    }
+    // A bare date means "as of the end of that day"
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(23, 59, 59))
+        .and_then(|dt| chrono::Local.from_local_datetime(&dt).single())
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
 fn get_machine_name() -> Option<String> {
     std::env::var("HOSTNAME")
         .or_else(|_| std::env::var("HOST"))
@@ -0,0 +1,303 @@
+use std::fmt;
+
+use crate::{AppsData, normalize_name};
+
+/// How a candidate matched the query, best first. Each kind scores within
+/// its own band so a better kind always outranks a worse one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchKind {
+    Exact,
+    Alias,
+    Normalized,
+    Prefix,
+    Contains,
+    /// The query contains the whole name
+    Within,
+    Subsequence,
+    Typo,
+}
+
+impl MatchKind {
+    fn base(self) -> u32 {
+        match self {
+            MatchKind::Exact => 1000,
+            MatchKind::Alias => 950,
+            MatchKind::Normalized => 900,
+            MatchKind::Prefix => 700,
+            MatchKind::Contains => 500,
+            MatchKind::Within => 400,
+            MatchKind::Subsequence => 300,
+            MatchKind::Typo => 200,
+        }
+    }
+}
+
+impl fmt::Display for MatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            MatchKind::Exact => "exact",
+            MatchKind::Alias => "alias",
+            MatchKind::Normalized => "same letters",
+            MatchKind::Prefix => "prefix",
+            MatchKind::Contains => "contains",
+            MatchKind::Within => "within query",
+            MatchKind::Subsequence => "letters in order",
+            MatchKind::Typo => "close spelling",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+pub struct Match<'a> {
+    pub key: &'a str,
+    pub kind: MatchKind,
+    pub score: u32,
+}
+
+pub enum Resolution<'a> {
+    Found(&'a str),
+    /// Several candidates scored too close together to pick one
+    Ambiguous(Vec<Match<'a>>),
+    NotFound,
+}
+
+// Candidates within this many points of the best are too close to call
+const AMBIGUITY_GAP: u32 = 50;
+
+/// Every app that matches `query` at all, best first.
+pub fn rank<'a>(data: &'a AppsData, query: &str) -> Vec<Match<'a>> {
+    let query_lower = query.to_lowercase();
+    let query_normalized = normalize_name(&query_lower);
+
+    let mut matches: Vec<Match> = data
+        .apps
+        .iter()
+        .filter_map(|(key, app)| {
+            let name_match = score(&key.to_lowercase(), &query_lower, &query_normalized);
+            // Aliases are only looked up exactly, but ahead of any fuzzy match
+            let is_alias = !query_normalized.is_empty() && app.aliases.iter().any(|a| normalize_name(a) == query_normalized);
+            let (kind, bonus) = match name_match {
+                Some((kind @ (MatchKind::Exact | MatchKind::Normalized), bonus)) => (kind, bonus),
+                _ if is_alias => (MatchKind::Alias, 0),
+                other => other?,
+            };
+            Some(Match { key, kind, score: kind.base() + bonus })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.key.cmp(b.key)));
+    matches
+}
+
+/// Pick the app `query` refers to, unless the best candidates are too close.
+pub fn resolve<'a>(data: &'a AppsData, query: &str) -> Resolution<'a> {
+    let mut matches = rank(data, query);
+    let Some(best) = matches.first() else {
+        return Resolution::NotFound;
+    };
+    if best.kind == MatchKind::Exact {
+        return Resolution::Found(best.key);
+    }
+    // Two apps that both spell the query differently, or share an alias,
+    // are equally likely however they score
+    if matches!(best.kind, MatchKind::Alias | MatchKind::Normalized) {
+        matches.retain(|m| matches!(m.kind, MatchKind::Alias | MatchKind::Normalized));
+        return if matches.len() == 1 { Resolution::Found(matches[0].key) } else { Resolution::Ambiguous(matches) };
+    }
+
+    let cutoff = best.score.saturating_sub(AMBIGUITY_GAP);
+    matches.retain(|m| m.score >= cutoff);
+    if matches.len() == 1 {
+        Resolution::Found(matches[0].key)
+    } else {
+        Resolution::Ambiguous(matches)
+    }
+}
+
+/// The app `query` names exactly or by alias, for commands that shouldn't
+/// act on a guess.
+pub fn resolve_exact<'a>(data: &'a AppsData, query: &str) -> Resolution<'a> {
+    let mut matches: Vec<Match> = rank(data, query)
+        .into_iter()
+        .filter(|m| matches!(m.kind, MatchKind::Exact | MatchKind::Alias))
+        .collect();
+    if let Some(exact) = matches.iter().find(|m| m.kind == MatchKind::Exact) {
+        return Resolution::Found(exact.key);
+    }
+    match matches.len() {
+        0 => Resolution::NotFound,
+        1 => Resolution::Found(matches.remove(0).key),
+        _ => Resolution::Ambiguous(matches),
+    }
+}
+
+/// The kind of match and a 0-99 bonus within its band.
+fn score(name: &str, query: &str, query_normalized: &str) -> Option<(MatchKind, u32)> {
+    if name == query {
+        return Some((MatchKind::Exact, 0));
+    }
+    let name_normalized = normalize_name(name);
+    if query_normalized.is_empty() || name_normalized.is_empty() {
+        return None;
+    }
+    if name_normalized == query_normalized {
+        return Some((MatchKind::Normalized, 0));
+    }
+
+    // Shorter names that the query covers more of rank higher
+    let coverage = |part: usize, whole: usize| (part * 99 / whole.max(1)) as u32;
+    let name_len = name_normalized.chars().count();
+    let query_len = query_normalized.chars().count();
+
+    if name_normalized.starts_with(query_normalized) {
+        return Some((MatchKind::Prefix, coverage(query_len, name_len)));
+    }
+    if name_normalized.contains(query_normalized) {
+        return Some((MatchKind::Contains, coverage(query_len, name_len)));
+    }
+    if query_normalized.contains(&name_normalized) {
+        return Some((MatchKind::Within, coverage(name_len, query_len)));
+    }
+    if let Some(span) = subsequence_span(&name_normalized, query_normalized) {
+        return Some((MatchKind::Subsequence, coverage(query_len, span)));
+    }
+
+    let distance = edit_distance(&name_normalized, query_normalized);
+    let allowed = (query_len / 4).max(1);
+    (distance <= allowed).then(|| (MatchKind::Typo, 99 - coverage(distance, query_len)))
+}
+
+/// Length of the shortest stretch of `name` holding the letters of `query`
+/// in order.
+fn subsequence_span(name: &str, query: &str) -> Option<usize> {
+    let name: Vec<char> = name.chars().collect();
+    let query: Vec<char> = query.chars().collect();
+
+    let mut best: Option<usize> = None;
+    for start in (0..name.len()).filter(|&i| name[i] == query[0]) {
+        let mut matched = 0;
+        for (i, &c) in name[start..].iter().enumerate() {
+            if c == query[matched] {
+                matched += 1;
+                if matched == query.len() {
+                    let span = i + 1;
+                    best = Some(best.map_or(span, |b| b.min(span)));
+                    break;
+                }
+            }
+        }
+    }
+    best
+}
+
+/// Levenshtein distance.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(apps: &[(&str, &[&str])]) -> AppsData {
+        let mut data = AppsData::default();
+        for (name, aliases) in apps {
+            let app = serde_json::from_value(serde_json::json!({
+                "name": name,
+                "aliases": aliases,
+                "github_repo": null,
+                "created_at": "2024-01-01T00:00:00Z",
+                "updated_at": "2024-01-01T00:00:00Z",
+            }))
+            .unwrap();
+            data.apps.insert(name.to_string(), app);
+        }
+        data
+    }
+
+    fn found<'a>(resolution: Resolution<'a>) -> Option<&'a str> {
+        match resolution {
+            Resolution::Found(key) => Some(key),
+            _ => None,
+        }
+    }
+
+    fn ambiguous(resolution: Resolution) -> Vec<String> {
+        match resolution {
+            Resolution::Ambiguous(matches) => matches.iter().map(|m| m.key.to_string()).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn bands_rank_better_kinds_first() {
+        let data = registry(&[("notes", &[]), ("keynotes", &[]), ("nts-cli", &[]), ("notez", &[])]);
+        let kinds: Vec<(&str, MatchKind)> = rank(&data, "notes").iter().map(|m| (m.key, m.kind)).collect();
+        assert_eq!(kinds[0], ("notes", MatchKind::Exact));
+        assert_eq!(kinds[1], ("keynotes", MatchKind::Contains));
+        assert_eq!(kinds[2], ("notez", MatchKind::Typo));
+        assert_eq!(kinds.len(), 3);
+
+        let kinds: Vec<MatchKind> = rank(&registry(&[("apps-helper", &[])]), "aph").iter().map(|m| m.kind).collect();
+        assert_eq!(kinds, [MatchKind::Subsequence]);
+    }
+
+    #[test]
+    fn exact_and_alias_matches_resolve() {
+        let data = registry(&[("apps-helper", &["ah"]), ("apps", &[])]);
+        assert_eq!(found(resolve(&data, "apps")), Some("apps"));
+        assert_eq!(found(resolve(&data, "APPS")), Some("apps"));
+        assert_eq!(found(resolve(&data, "ah")), Some("apps-helper"));
+        assert_eq!(found(resolve(&data, "AppsHelper")), Some("apps-helper"));
+    }
+
+    #[test]
+    fn close_scores_are_ambiguous_and_distant_ones_are_not() {
+        let data = registry(&[("web-api", &[]), ("web-app", &[])]);
+        assert_eq!(ambiguous(resolve(&data, "web")), ["web-api", "web-app"]);
+
+        // A prefix outranks a contains hit by more than the gap
+        let data = registry(&[("webshop", &[]), ("my-website", &[])]);
+        assert_eq!(found(resolve(&data, "web")), Some("webshop"));
+    }
+
+    #[test]
+    fn several_normalized_or_alias_matches_are_ambiguous() {
+        let data = registry(&[("my-app", &[]), ("my_app", &[])]);
+        assert_eq!(ambiguous(resolve(&data, "myapp")), ["my-app", "my_app"]);
+
+        let data = registry(&[("one", &["x"]), ("two", &["x"])]);
+        assert_eq!(ambiguous(resolve(&data, "x")), ["one", "two"]);
+    }
+
+    #[test]
+    fn resolve_exact_ignores_fuzzy_matches() {
+        let data = registry(&[("apps-helper", &["ah"]), ("webshop", &[])]);
+        assert_eq!(found(resolve_exact(&data, "apps-helper")), Some("apps-helper"));
+        assert_eq!(found(resolve_exact(&data, "ah")), Some("apps-helper"));
+        assert!(matches!(resolve_exact(&data, "web"), Resolution::NotFound));
+        assert!(matches!(resolve_exact(&data, "appshelper"), Resolution::NotFound));
+    }
+
+    #[test]
+    fn subsequence_span_finds_the_tightest_stretch() {
+        assert_eq!(subsequence_span("abxcabc", "abc"), Some(3));
+        assert_eq!(subsequence_span("abc", "acb"), None);
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
@@ -0,0 +1,296 @@
+use chrono::DateTime;
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::{App, AppProfile, AppsData};
+
+/// A field both sides set to different values; `kept` won by recency.
+pub struct Conflict {
+    pub app: String,
+    pub field: String,
+    pub kept: String,
+    pub dropped: String,
+    pub kept_from: Side,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Side {
+    Local,
+    Other,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Local => write!(f, "local"),
+            Side::Other => write!(f, "other"),
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} kept \"{}\" ({}, newer) over \"{}\"",
+            self.app, self.field, self.kept, self.kept_from, self.dropped
+        )
+    }
+}
+
+pub struct MergeOutcome {
+    pub data: AppsData,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Combine two registries: apps are unioned by name, profiles by
+/// (type, machine), tags and tasks are deduplicated, and fields that
+/// disagree take the value from the more recently updated side.
+pub fn merge(local: &AppsData, other: &AppsData) -> MergeOutcome {
+    let mut data = local.clone();
+    let mut conflicts = Vec::new();
+
+    let mut names: Vec<&String> = other.apps.keys().collect();
+    names.sort();
+    for name in names {
+        let other_app = &other.apps[name];
+        match local.apps.get(name) {
+            None => {
+                data.apps.insert(name.clone(), other_app.clone());
+            }
+            Some(local_app) => {
+                let merged = merge_app(local_app, other_app, &mut conflicts);
+                data.apps.insert(name.clone(), merged);
+            }
+        }
+    }
+
+    MergeOutcome { data, conflicts }
+}
+
+fn merge_app(local: &App, other: &App, conflicts: &mut Vec<Conflict>) -> App {
+    let newer = match compare_timestamps(&other.updated_at, &local.updated_at) {
+        Ordering::Greater => Side::Other,
+        _ => Side::Local,
+    };
+    let (newer_app, older_app) = match newer {
+        Side::Local => (local, other),
+        Side::Other => (other, local),
+    };
+
+    let mut merged = local.clone();
+
+    merged.github_repo = match (&newer_app.github_repo, &older_app.github_repo) {
+        (Some(a), Some(b)) if a != b => {
+            conflicts.push(Conflict {
+                app: local.name.clone(),
+                field: "github_repo".to_string(),
+                kept: a.clone(),
+                dropped: b.clone(),
+                kept_from: newer,
+            });
+            Some(a.clone())
+        }
+        // A repo cleared on the newer side stays cleared
+        (a, _) => a.clone(),
+    };
+
+    // A preference rather than data; the newer choice wins without a conflict
+    merged.opener = newer_app.opener;
+    merged.layout = newer_app.layout.clone();
+
+    merged.tags = union(&local.tags, &other.tags);
+    merged.aliases = union(&local.aliases, &other.aliases);
+    merged.tasks = union(&local.tasks, &other.tasks);
+
+    merged.created_at = match compare_timestamps(&other.created_at, &local.created_at) {
+        Ordering::Less => other.created_at.clone(),
+        _ => local.created_at.clone(),
+    };
+    merged.updated_at = newer_app.updated_at.clone();
+
+    merged.profiles = merge_profiles(&local.name, newer_app, older_app, newer, conflicts);
+    merged
+}
+
+fn merge_profiles(app_name: &str, newer: &App, older: &App, newer_side: Side, conflicts: &mut Vec<Conflict>) -> Vec<AppProfile> {
+    let mut profiles: Vec<AppProfile> = newer.profiles.clone();
+
+    for old_profile in &older.profiles {
+        match profiles.iter().find(|p| p.same_slot(old_profile)) {
+            None => {
+                let mut profile = old_profile.clone();
+                profile.active = false;
+                profiles.push(profile);
+            }
+            Some(kept) => {
+                let slot = format!("{:?} profile{}", kept.profile_type, machine_suffix(&kept.machine_name));
+                let mut report = |field: &str, kept: String, dropped: String| {
+                    if kept != dropped {
+                        conflicts.push(Conflict {
+                            app: app_name.to_string(),
+                            field: format!("{} {}", slot, field),
+                            kept,
+                            dropped,
+                            kept_from: newer_side,
+                        });
+                    }
+                };
+                report("location", kept.location.display().to_string(), old_profile.location.display().to_string());
+                if let (Some(a), Some(b)) = (&kept.notes, &old_profile.notes) {
+                    report("notes", a.clone(), b.clone());
+                }
+            }
+        }
+    }
+
+    // Fill in notes only the older side had
+    for profile in &mut profiles {
+        if profile.notes.is_none()
+            && let Some(old_profile) = older.profiles.iter().find(|p| p.same_slot(profile))
+        {
+            profile.notes = old_profile.notes.clone();
+        }
+    }
+
+    // Exactly one active profile: the newer side's choice wins
+    if !profiles.is_empty() && !profiles.iter().any(|p| p.active) {
+        profiles[0].active = true;
+    }
+    profiles
+}
+
+fn machine_suffix(machine: &Option<String>) -> String {
+    machine.as_ref().map(|m| format!(" on {}", m)).unwrap_or_default()
+}
+
+/// `a` followed by the items of `b` it doesn't already contain.
+fn union(a: &[String], b: &[String]) -> Vec<String> {
+    let mut result = a.to_vec();
+    for item in b {
+        if !result.contains(item) {
+            result.push(item.clone());
+        }
+    }
+    result
+}
+
+fn compare_timestamps(a: &str, b: &str) -> Ordering {
+    match (DateTime::parse_from_rfc3339(a), DateTime::parse_from_rfc3339(b)) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(updated_at: &str, value: serde_json::Value) -> App {
+        let mut fields = serde_json::json!({
+            "name": "notes",
+            "github_repo": null,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": updated_at,
+        });
+        fields.as_object_mut().unwrap().extend(value.as_object().unwrap().clone());
+        serde_json::from_value(fields).unwrap()
+    }
+
+    fn profile(profile_type: &str, location: &str, notes: Option<&str>, active: bool) -> serde_json::Value {
+        serde_json::json!({ "profile_type": profile_type, "location": location, "machine_name": null, "notes": notes, "active": active })
+    }
+
+    /// Merge one app from each side and return the result and the conflicts.
+    fn merge_one(local: App, other: App) -> (App, Vec<String>) {
+        let mut local_data = AppsData::default();
+        local_data.apps.insert("notes".to_string(), local);
+        let mut other_data = AppsData::default();
+        other_data.apps.insert("notes".to_string(), other);
+        let outcome = merge(&local_data, &other_data);
+        let conflicts = outcome.conflicts.iter().map(|c| c.to_string()).collect();
+        (outcome.data.apps["notes"].clone(), conflicts)
+    }
+
+    const OLDER: &str = "2024-01-01T00:00:00Z";
+    const NEWER: &str = "2024-06-01T00:00:00Z";
+
+    #[test]
+    fn lists_are_unioned_local_first() {
+        let local = app(OLDER, serde_json::json!({ "tags": ["rust", "cli"], "aliases": ["nt"], "tasks": ["ship"] }));
+        let other = app(NEWER, serde_json::json!({ "tags": ["cli", "web"], "aliases": ["n"], "tasks": ["ship", "docs"] }));
+        let (merged, conflicts) = merge_one(local, other);
+        assert_eq!(merged.tags, ["rust", "cli", "web"]);
+        assert_eq!(merged.aliases, ["nt", "n"]);
+        assert_eq!(merged.tasks, ["ship", "docs"]);
+        assert_eq!(merged.updated_at, NEWER);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn the_newer_side_wins_single_values_even_when_cleared() {
+        let set = app(OLDER, serde_json::json!({
+            "github_repo": "me/notes",
+            "opener": "terminal",
+            "layout": [{ "name": "main", "panes": [{ "command": "cargo watch", "profile_type": null }] }],
+        }));
+        let cleared = app(NEWER, serde_json::json!({}));
+        let (merged, conflicts) = merge_one(set.clone(), cleared.clone());
+        assert_eq!(merged.github_repo, None);
+        assert_eq!(merged.opener, None);
+        assert!(merged.layout.is_empty());
+        assert!(conflicts.is_empty());
+
+        // And the other way round
+        let mut newer_set = set;
+        newer_set.updated_at = "2024-09-01T00:00:00Z".to_string();
+        let (merged, _) = merge_one(cleared, newer_set.clone());
+        assert_eq!(merged.github_repo, newer_set.github_repo);
+        assert_eq!(merged.opener, newer_set.opener);
+        assert_eq!(merged.layout, newer_set.layout);
+    }
+
+    #[test]
+    fn values_both_sides_set_differently_are_reported() {
+        let local = app(NEWER, serde_json::json!({ "github_repo": "me/notes" }));
+        let other = app(OLDER, serde_json::json!({ "github_repo": "old/notes" }));
+        let (merged, conflicts) = merge_one(local, other);
+        assert_eq!(merged.github_repo.as_deref(), Some("me/notes"));
+        assert_eq!(conflicts, ["notes: github_repo kept \"me/notes\" (local, newer) over \"old/notes\""]);
+    }
+
+    #[test]
+    fn profiles_merge_by_slot_and_the_newer_side_keeps_the_active_one() {
+        let local = app(OLDER, serde_json::json!({
+            "profiles": [profile("dev", "/old/notes", Some("first clone"), true), profile("config", "/etc/notes", None, false)],
+        }));
+        let other = app(NEWER, serde_json::json!({
+            "profiles": [profile("binary", "/usr/bin/notes", None, true), profile("dev", "/src/notes", None, false)],
+        }));
+        let (merged, conflicts) = merge_one(local, other);
+        let slots: Vec<(String, bool)> =
+            merged.profiles.iter().map(|p| (p.location.display().to_string(), p.active)).collect();
+        assert_eq!(slots, [
+            ("/usr/bin/notes".to_string(), true),
+            ("/src/notes".to_string(), false),
+            ("/etc/notes".to_string(), false),
+        ]);
+        // Notes only the older side had are filled in
+        assert_eq!(merged.profiles[1].notes.as_deref(), Some("first clone"));
+        assert_eq!(conflicts, ["notes: Dev profile location kept \"/src/notes\" (other, newer) over \"/old/notes\""]);
+    }
+
+    #[test]
+    fn the_earliest_creation_time_is_kept_and_new_apps_are_added() {
+        let mut local = app(OLDER, serde_json::json!({}));
+        local.created_at = "2023-05-01T00:00:00Z".to_string();
+        let other = app(NEWER, serde_json::json!({}));
+        let (merged, _) = merge_one(local, other.clone());
+        assert_eq!(merged.created_at, "2023-05-01T00:00:00Z");
+
+        let mut other_data = AppsData::default();
+        other_data.apps.insert("fresh".to_string(), app(NEWER, serde_json::json!({ "name": "fresh" })));
+        let outcome = merge(&AppsData::default(), &other_data);
+        assert!(outcome.data.apps.contains_key("fresh"));
+    }
+}
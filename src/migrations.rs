@@ -0,0 +1,242 @@
+use anyhow::Result;
+use serde_json::{Map, Value, json};
+
+/// Schema version written by this build. Bump it together with a new entry in `MIGRATIONS`.
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&mut Map<String, Value>) -> Vec<String>,
+}
+
+// Ordered chain of upgrade steps; entry N upgrades version N to N + 1
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "Move legacy `directory` fields into Dev profiles",
+    apply: migrate_directory_to_profiles,
+}];
+
+pub struct MigrationStep {
+    pub from: u32,
+    pub to: u32,
+    pub description: &'static str,
+    pub changes: Vec<String>,
+}
+
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub steps: Vec<MigrationStep>,
+}
+
+impl MigrationReport {
+    pub fn up_to_date(version: u32) -> MigrationReport {
+        MigrationReport {
+            from_version: version,
+            to_version: version,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn is_upgrade(&self) -> bool {
+        self.from_version != self.to_version
+    }
+}
+
+/// Upgrade a raw apps.json document in place to `CURRENT_SCHEMA_VERSION`.
+pub fn migrate(document: &mut Value) -> Result<MigrationReport> {
+    let root = document
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("Data file must contain a JSON object"))?;
+
+    let from_version = match root.get("schema_version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid schema_version: {}", v))?,
+    };
+
+    if from_version > CURRENT_SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "Data file has schema version {}, but this apps-helper only supports up to {}. Please upgrade apps-helper.",
+            from_version,
+            CURRENT_SCHEMA_VERSION
+        ));
+    }
+
+    let mut steps = Vec::new();
+    let mut version = from_version;
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from_version) {
+        let changes = (migration.apply)(root);
+        version = migration.from + 1;
+        steps.push(MigrationStep {
+            from: migration.from,
+            to: version,
+            description: migration.description,
+            changes,
+        });
+    }
+    root.insert("schema_version".to_string(), json!(version));
+
+    Ok(MigrationReport {
+        from_version,
+        to_version: version,
+        steps,
+    })
+}
+
+fn migrate_directory_to_profiles(root: &mut Map<String, Value>) -> Vec<String> {
+    let mut changes = Vec::new();
+    let Some(apps) = root.get_mut("apps").and_then(Value::as_object_mut) else {
+        return changes;
+    };
+
+    for (name, app) in apps.iter_mut() {
+        let Some(app) = app.as_object_mut() else {
+            continue;
+        };
+        let Some(directory) = app.remove("directory") else {
+            continue;
+        };
+        let Some(directory) = directory.as_str().map(str::to_string) else {
+            // null directory: nothing to keep
+            continue;
+        };
+
+        let profiles = app
+            .entry("profiles")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        let Some(profiles) = profiles.as_array_mut() else {
+            continue;
+        };
+
+        let already_covered = profiles.iter().any(|p| {
+            p.get("location").and_then(Value::as_str) == Some(directory.as_str())
+        });
+        if already_covered {
+            changes.push(format!("{}: dropped legacy directory {} (already a profile location)", name, directory));
+            continue;
+        }
+
+        // The directory was a source checkout, so it only becomes a Dev
+        // profile; when there is one already, keep the path in its notes
+        let dev = profiles.iter_mut().find(|p| {
+            p.get("profile_type").and_then(Value::as_str) == Some("dev")
+                && p.get("machine_name").is_none_or(Value::is_null)
+        });
+        match dev.and_then(Value::as_object_mut) {
+            None => {
+                let active = profiles.is_empty();
+                profiles.push(json!({
+                    "profile_type": "dev",
+                    "location": directory,
+                    "machine_name": null,
+                    "notes": null,
+                    "active": active,
+                }));
+                changes.push(format!("{}: moved legacy directory {} into a Dev profile", name, directory));
+            }
+            Some(dev) => {
+                let note = format!("Legacy directory: {}", directory);
+                let notes = match dev.get("notes").and_then(Value::as_str) {
+                    Some(existing) if !existing.is_empty() => format!("{}\n{}", existing, note),
+                    _ => note,
+                };
+                dev.insert("notes".to_string(), json!(notes));
+                changes.push(format!("{}: kept legacy directory {} in the notes of its Dev profile", name, directory));
+            }
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy() -> Value {
+        json!({
+            "apps": {
+                "plain": { "name": "plain", "directory": "/src/plain", "tags": [] },
+                "covered": {
+                    "name": "covered",
+                    "directory": "/src/covered",
+                    "profiles": [{ "profile_type": "dev", "location": "/src/covered", "machine_name": null, "notes": null, "active": true }]
+                },
+                "installed": {
+                    "name": "installed",
+                    "directory": "/src/installed",
+                    "profiles": [{ "profile_type": "installed", "location": "/opt/installed", "machine_name": null, "notes": null, "active": true }]
+                },
+                "has-dev": {
+                    "name": "has-dev",
+                    "directory": "/old/checkout",
+                    "profiles": [{ "profile_type": "dev", "location": "/src/has-dev", "machine_name": null, "notes": "main clone", "active": true }]
+                },
+                "no-directory": { "name": "no-directory", "directory": null }
+            }
+        })
+    }
+
+    #[test]
+    fn the_chain_runs_from_the_stored_version_to_the_current_one() {
+        let mut document = legacy();
+        let report = migrate(&mut document).unwrap();
+        assert!(report.is_upgrade());
+        assert_eq!((report.from_version, report.to_version), (0, CURRENT_SCHEMA_VERSION));
+        let steps: Vec<(u32, u32)> = report.steps.iter().map(|s| (s.from, s.to)).collect();
+        let expected: Vec<(u32, u32)> = (0..CURRENT_SCHEMA_VERSION).map(|v| (v, v + 1)).collect();
+        assert_eq!(steps, expected);
+        assert_eq!(document["schema_version"], json!(CURRENT_SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn migrating_twice_changes_nothing_the_second_time() {
+        let mut document = legacy();
+        migrate(&mut document).unwrap();
+        let once = document.clone();
+        let report = migrate(&mut document).unwrap();
+        assert!(!report.is_upgrade());
+        assert!(report.steps.is_empty());
+        assert_eq!(document, once);
+    }
+
+    #[test]
+    fn newer_and_invalid_versions_are_refused() {
+        let mut newer = json!({ "schema_version": CURRENT_SCHEMA_VERSION + 1, "apps": {} });
+        assert!(migrate(&mut newer).is_err());
+        let mut invalid = json!({ "schema_version": "one", "apps": {} });
+        assert!(migrate(&mut invalid).is_err());
+        assert!(migrate(&mut json!([])).is_err());
+    }
+
+    #[test]
+    fn legacy_directories_become_dev_profiles_or_notes() {
+        let mut document = legacy();
+        migrate(&mut document).unwrap();
+        let apps = &document["apps"];
+        for app in apps.as_object().unwrap().values() {
+            assert!(app.get("directory").is_none());
+        }
+
+        assert_eq!(apps["plain"]["profiles"], json!([
+            { "profile_type": "dev", "location": "/src/plain", "machine_name": null, "notes": null, "active": true }
+        ]));
+        assert_eq!(apps["covered"]["profiles"].as_array().unwrap().len(), 1);
+
+        // A checkout is never filed under another type
+        let installed = apps["installed"]["profiles"].as_array().unwrap();
+        assert_eq!(installed.len(), 2);
+        assert_eq!(installed[1]["profile_type"], "dev");
+        assert_eq!(installed[1]["active"], false);
+
+        let has_dev = apps["has-dev"]["profiles"].as_array().unwrap();
+        assert_eq!(has_dev.len(), 1);
+        assert_eq!(has_dev[0]["notes"], "main clone\nLegacy directory: /old/checkout");
+
+        assert!(apps["no-directory"].get("profiles").is_none());
+    }
+}
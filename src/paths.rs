@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DATA_FILE_NAME: &str = "apps.json";
+
+/// Where the data lives when `--data-file` is not given:
+/// `$APPS_HELPER_HOME`, then `$XDG_DATA_HOME/apps-helper`, then
+/// `$HOME/.local/share/apps-helper`.
+pub fn default_data_dir() -> Result<PathBuf> {
+    if let Some(dir) = env_path("APPS_HELPER_HOME") {
+        return Ok(dir);
+    }
+    xdg_data_dir()
+}
+
+fn xdg_data_dir() -> Result<PathBuf> {
+    // The XDG spec says relative paths must be ignored
+    if let Some(dir) = env_path("XDG_DATA_HOME").filter(|p| p.is_absolute()) {
+        return Ok(dir.join("apps-helper"));
+    }
+    let home = home_dir()?;
+    Ok(home.join(".local").join("share").join("apps-helper"))
+}
+
+fn home_dir() -> Result<PathBuf> {
+    env_path("HOME").ok_or_else(|| {
+        anyhow::anyhow!("Cannot locate the data directory: HOME is not set. Use --data-file or set APPS_HELPER_HOME.")
+    })
+}
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from)
+}
+
+/// Move data from the pre-XDG `~/.apps-helper` directory to the XDG location
+/// the first time we run with it. Only applies when no explicit location is
+/// configured.
+pub fn migrate_legacy_data_dir() -> Result<()> {
+    if env_path("APPS_HELPER_HOME").is_some() {
+        return Ok(());
+    }
+    let Ok(home) = home_dir() else {
+        return Ok(());
+    };
+    let legacy_dir = home.join(".apps-helper");
+    let new_dir = xdg_data_dir()?;
+    if !legacy_dir.join(DATA_FILE_NAME).exists() || new_dir.join(DATA_FILE_NAME).exists() {
+        return Ok(());
+    }
+
+    if let Some(parent) = new_dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // An empty target directory would make the rename fail
+    let _ = fs::remove_dir(&new_dir);
+    if fs::rename(&legacy_dir, &new_dir).is_err() {
+        if !legacy_dir.exists() && new_dir.join(DATA_FILE_NAME).exists() {
+            // Another process moved it first
+            return Ok(());
+        }
+        // Different filesystem: copy, then drop the original
+        copy_dir(&legacy_dir, &new_dir)
+            .with_context(|| format!("Failed to move {} to {}", legacy_dir.display(), new_dir.display()))?;
+        fs::remove_dir_all(&legacy_dir)?;
+    }
+    eprintln!("Moved data from {} to {}", legacy_dir.display(), new_dir.display());
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
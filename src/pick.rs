@@ -0,0 +1,215 @@
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::{cursor, queue, terminal};
+use std::io::{self, Write};
+
+use crate::{App, AppsData, format_datetime, matcher};
+
+/// Lets the user choose one of `keys` in a full-screen fuzzy finder that
+/// narrows the list as they type, with details of the highlighted app
+/// alongside. It draws on stderr so stdout stays free for the answer, as
+/// in `cd "$(apps-helper pick --path)"`. Returns None when cancelled.
+pub fn pick(data: &AppsData, keys: &[&str], query: &str) -> Result<Option<String>> {
+    let mut picker = Picker { data, keys, query: query.to_string(), selected: 0, scroll: 0 };
+    let _screen = Screen::enter()?;
+    let mut err = io::stderr();
+
+    loop {
+        let shown = picker.filtered();
+        picker.selected = picker.selected.min(shown.len().saturating_sub(1));
+        picker.draw(&mut err, &shown)?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c' | 'g') if ctrl => return Ok(None),
+            KeyCode::Enter => return Ok(shown.get(picker.selected).map(|key| key.to_string())),
+            KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+            KeyCode::Char('p' | 'k') if ctrl => picker.selected = picker.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Tab => picker.selected += 1,
+            KeyCode::Char('n' | 'j') if ctrl => picker.selected += 1,
+            KeyCode::PageUp => picker.selected = picker.selected.saturating_sub(10),
+            KeyCode::PageDown => picker.selected += 10,
+            KeyCode::Char('u') if ctrl => picker.set_query(String::new()),
+            KeyCode::Backspace => {
+                let mut query = picker.query.clone();
+                query.pop();
+                picker.set_query(query);
+            }
+            KeyCode::Char(c) if !ctrl => {
+                let query = format!("{}{}", picker.query, c);
+                picker.set_query(query);
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Picker<'a> {
+    data: &'a AppsData,
+    keys: &'a [&'a str],
+    query: String,
+    selected: usize,
+    // First row of the list that's on screen
+    scroll: usize,
+}
+
+impl<'a> Picker<'a> {
+    fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    /// The keys matching the query, best first; all of them, in the
+    /// given order, while it's empty.
+    fn filtered(&self) -> Vec<&'a str> {
+        if self.query.trim().is_empty() {
+            return self.keys.to_vec();
+        }
+        matcher::rank(self.data, &self.query)
+            .into_iter()
+            .filter_map(|m| self.keys.iter().copied().find(|&key| key == m.key))
+            .collect()
+    }
+
+    fn draw(&mut self, out: &mut impl Write, shown: &[&str]) -> Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+        // The preview goes beside the list on wide terminals, under it otherwise
+        let side_by_side = width >= 80;
+        let list_width = if side_by_side { width * 2 / 5 } else { width };
+        let list_height = if side_by_side { height.saturating_sub(1) } else { height.saturating_sub(2) / 2 };
+
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if list_height > 0 && self.selected >= self.scroll + list_height {
+            self.scroll = self.selected + 1 - list_height;
+        }
+
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+        for (row, &key) in shown.iter().enumerate().skip(self.scroll).take(list_height) {
+            let line = fit(&list_line(&self.data.apps[key]), list_width.saturating_sub(2));
+            queue!(out, cursor::MoveTo(0, (row - self.scroll + 1) as u16))?;
+            if row == self.selected {
+                queue!(out, SetAttribute(Attribute::Reverse), Print(format!("> {}", line)), SetAttribute(Attribute::Reset))?;
+            } else {
+                queue!(out, Print(format!("  {}", line)))?;
+            }
+        }
+
+        if let Some(&key) = shown.get(self.selected) {
+            let lines = preview_lines(&self.data.apps[key]);
+            let (column, first_row, preview_width, preview_height) = if side_by_side {
+                for row in 1..height {
+                    queue!(out, cursor::MoveTo(list_width as u16, row as u16), Print("│"))?;
+                }
+                (list_width + 2, 1, width.saturating_sub(list_width + 2), height.saturating_sub(1))
+            } else {
+                queue!(out, cursor::MoveTo(0, (list_height + 1) as u16), Print("─".repeat(width)))?;
+                (0, list_height + 2, width, height.saturating_sub(list_height + 2))
+            };
+            for (row, line) in lines.iter().take(preview_height).enumerate() {
+                queue!(out, cursor::MoveTo(column as u16, (first_row + row) as u16), Print(fit(line, preview_width)))?;
+            }
+        }
+
+        let count = format!(" {}/{}", shown.len(), self.keys.len());
+        queue!(
+            out,
+            cursor::MoveTo(width.saturating_sub(count.chars().count()) as u16, 0),
+            SetAttribute(Attribute::Dim),
+            Print(count),
+            SetAttribute(Attribute::Reset),
+            cursor::MoveTo(0, 0),
+            Print(fit(&format!("> {}", self.query), width.saturating_sub(12))),
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// `name [tags] dev · latest task`
+fn list_line(app: &App) -> String {
+    let mut line = app.name.clone();
+    if !app.tags.is_empty() {
+        line.push_str(&format!(" [{}]", app.tags.join(", ")));
+    }
+    if let Some(profile) = app.profiles.iter().find(|p| p.active) {
+        line.push_str(&format!(" {}", format!("{:?}", profile.profile_type).to_lowercase()));
+    }
+    if let Some(task) = app.tasks.last() {
+        line.push_str(&format!(" · {}", task));
+    }
+    line
+}
+
+fn preview_lines(app: &App) -> Vec<String> {
+    let mut lines = vec![app.name.clone(), String::new()];
+    if !app.aliases.is_empty() {
+        lines.push(format!("Aliases: {}", app.aliases.join(", ")));
+    }
+    if !app.tags.is_empty() {
+        lines.push(format!("Tags: {}", app.tags.join(", ")));
+    }
+    if let Some(repo) = &app.github_repo {
+        lines.push(format!("GitHub: {}", repo));
+    }
+    if !app.profiles.is_empty() {
+        lines.push("Profiles:".to_string());
+        for profile in &app.profiles {
+            let active = if profile.active { " (active)" } else { "" };
+            lines.push(format!("  {:?}: {}{}", profile.profile_type, profile.location.display(), active));
+        }
+    }
+    if app.tasks.is_empty() {
+        lines.push("No open tasks".to_string());
+    } else {
+        lines.push("Tasks:".to_string());
+        for (i, task) in app.tasks.iter().enumerate() {
+            lines.push(format!("  {}. {}", i + 1, task));
+        }
+    }
+    lines.push(format!("Updated: {}", format_datetime(&app.updated_at)));
+    lines
+}
+
+/// `text` cut to `width` characters, ending in `…` if anything was cut.
+fn fit(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut fitted: String = text.chars().take(width.saturating_sub(1)).collect();
+    if width > 0 {
+        fitted.push('…');
+    }
+    fitted
+}
+
+/// Raw mode on the alternate screen, restored when dropped so an error
+/// doesn't leave the terminal unusable.
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Screen> {
+        terminal::enable_raw_mode()?;
+        let screen = Screen;
+        queue!(io::stderr(), terminal::EnterAlternateScreen)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = queue!(io::stderr(), terminal::LeaveAlternateScreen);
+        let _ = io::stderr().flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
@@ -0,0 +1,253 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::{AppsData, ProfileType, locate, storage};
+
+pub const DEFAULT_FORMAT: &str = "{name}:{type}:{tasks}";
+
+/// Shell to escape the prompt segment for, so app names can't inject
+/// prompt escapes or command substitutions.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    /// No escaping, e.g. for starship
+    None,
+}
+
+/// Size and modification time of the data file when the index was built;
+/// a different stamp means the index is stale.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Stamp {
+    len: u64,
+    modified_nanos: u64,
+}
+
+impl Stamp {
+    pub fn of(data_file: &Path) -> Option<Stamp> {
+        let metadata = fs::metadata(data_file).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Stamp { len: metadata.len(), modified_nanos: modified.as_nanos() as u64 })
+    }
+}
+
+/// What the prompt shows for a directory inside one profile location.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    location: PathBuf,
+    app: String,
+    inside: ProfileType,
+    active: Option<ProfileType>,
+    tasks: usize,
+}
+
+/// Profile locations mapped to apps, kept small so the prompt can answer
+/// without loading the registry. Entries are in lookup order: deepest
+/// location first, then this machine's and active profiles, as in
+/// `locate::find_by_dir`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Index {
+    stamp: Option<Stamp>,
+    entries: Vec<Entry>,
+}
+
+impl Index {
+    pub fn build(data: &AppsData, stamp: Option<Stamp>, machine: Option<&str>) -> Index {
+        let mut ranked: Vec<(usize, bool, bool, &str, Entry)> = data
+            .apps
+            .iter()
+            .flat_map(|(key, app)| {
+                let active = app.profiles.iter().find(|p| p.active).map(|p| p.profile_type);
+                app.profiles.iter().map(move |profile| {
+                    let location = locate::canonical(&profile.location);
+                    let entry = Entry { app: app.name.clone(), inside: profile.profile_type, active, tasks: app.tasks.len(), location };
+                    let on_machine = profile.machine_name.as_deref() == machine;
+                    (entry.location.components().count(), on_machine, profile.active, key.as_str(), entry)
+                })
+            })
+            .collect();
+        ranked.sort_by(|a, b| (Reverse(a.0), !a.1, !a.2, a.3).cmp(&(Reverse(b.0), !b.1, !b.2, b.3)));
+        Index { stamp, entries: ranked.into_iter().map(|(_, _, _, _, entry)| entry).collect() }
+    }
+
+    /// The index at `path` if it was built from a data file with `stamp`.
+    pub fn load_fresh(path: &Path, stamp: Option<Stamp>) -> Option<Index> {
+        let content = fs::read(path).ok()?;
+        let index: Index = serde_json::from_slice(&content).ok()?;
+        (index.stamp == stamp).then_some(index)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        storage::write_atomic(path, &serde_json::to_vec(self)?)
+    }
+
+    pub fn find(&self, dir: &Path) -> Option<&Entry> {
+        let dir = locate::canonical(dir);
+        self.entries.iter().find(|entry| dir.starts_with(&entry.location))
+    }
+}
+
+enum Segment {
+    Text(String),
+    Name,
+    Type,
+    Inside,
+    Tasks,
+}
+
+/// A parsed format string. `{name}`, `{type}` (the active profile type),
+/// `{inside}` (the type of the profile we're in) and `{tasks}` are
+/// replaced; `{{` and `}}` are literal braces.
+pub struct Format(Vec<Segment>);
+
+impl Format {
+    /// Parse `format`; errors point at the placeholder that's wrong.
+    pub fn parse(format: &str) -> Result<Format> {
+        let error = |message: &str, span: std::ops::Range<usize>| {
+            let caret = " ".repeat(format[..span.start].chars().count()) + &"^".repeat(format[span].chars().count().max(1));
+            anyhow::anyhow!("Invalid prompt format: {}\n  {}\n  {}", message, format, caret)
+        };
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = format.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().is_some_and(|&(_, c)| c == '{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek().is_some_and(|&(_, c)| c == '}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let Some(end) = format[start..].find('}').map(|i| start + i) else {
+                        return Err(error("'{' is never closed (write {{ for a literal brace)", start..start + 1));
+                    };
+                    let name = &format[start + 1..end];
+                    while chars.next_if(|&(i, _)| i <= end).is_some() {}
+                    let segment = match name {
+                        "name" => Segment::Name,
+                        "type" => Segment::Type,
+                        "inside" => Segment::Inside,
+                        "tasks" => Segment::Tasks,
+                        _ => {
+                            let message = format!("unknown placeholder {{{}}} (expected {{name}}, {{type}}, {{inside}} or {{tasks}})", name);
+                            return Err(error(&message, start..end + 1));
+                        }
+                    };
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                    segments.push(segment);
+                }
+                _ => text.push(c),
+            }
+        }
+        segments.push(Segment::Text(text));
+        Ok(Format(segments))
+    }
+
+    pub fn render(&self, entry: &Entry, shell: Shell) -> String {
+        let type_name = |t: ProfileType| t.to_possible_value().map_or_else(String::new, |v| v.get_name().to_string());
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.clone(),
+                Segment::Name => escape(&entry.app, shell),
+                Segment::Type => entry.active.map(type_name).unwrap_or_default(),
+                Segment::Inside => type_name(entry.inside),
+                Segment::Tasks => entry.tasks.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Make `text` inert in the prompt. Shells expand a prompt segment once
+/// more when it is pasted into `PS1` rather than printed by a `$(...)` in
+/// it, and no backslash survives both forms, so `$`, `` ` `` and `\` show
+/// as `?` instead of being escaped.
+fn escape(text: &str, shell: Shell) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match (shell, c) {
+            (Shell::Zsh, '%') => escaped.push_str("%%"),
+            (Shell::Bash | Shell::Zsh, '\\' | '$' | '`') => escaped.push('?'),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> AppsData {
+        let app = serde_json::from_value(serde_json::json!({
+            "name": "x$(echo PWNED)",
+            "tasks": ["ship"],
+            "github_repo": null,
+            "profiles": [
+                { "profile_type": "dev", "location": "/src/notes", "machine_name": null, "notes": null, "active": true },
+                { "profile_type": "installed", "location": "/src/notes/dist", "machine_name": null, "notes": null, "active": false },
+            ],
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        let mut data = AppsData::default();
+        data.apps.insert("notes".to_string(), app);
+        data
+    }
+
+    #[test]
+    fn escape_leaves_nothing_the_shell_expands() {
+        assert_eq!(escape("x$(echo PWNED)", Shell::Bash), "x?(echo PWNED)");
+        assert_eq!(escape("a`id`\\b", Shell::Bash), "a?id??b");
+        assert_eq!(escape("100%$", Shell::Zsh), "100%%?");
+        assert_eq!(escape("100%$", Shell::Bash), "100%?");
+        assert_eq!(escape("x$(echo PWNED)", Shell::None), "x$(echo PWNED)");
+    }
+
+    #[test]
+    fn format_renders_placeholders_and_literal_braces() {
+        let index = Index::build(&data(), None, None);
+        let format = Format::parse("{{{name}}} {type}/{inside} {tasks}").unwrap();
+        let entry = index.find(Path::new("/src/notes/dist/bin")).unwrap();
+        assert_eq!(format.render(entry, Shell::Bash), "{x?(echo PWNED)} dev/installed 1");
+        let entry = index.find(Path::new("/src/notes/src")).unwrap();
+        assert_eq!(format.render(entry, Shell::None), "{x$(echo PWNED)} dev/dev 1");
+        assert!(index.find(Path::new("/src/other")).is_none());
+    }
+
+    #[test]
+    fn format_errors_point_at_the_placeholder() {
+        let error = Format::parse("{name} {nmae}").err().unwrap().to_string();
+        assert!(error.contains("unknown placeholder {nmae}"), "{}", error);
+        assert!(error.ends_with("\n         ^^^^^^"), "{}", error);
+        let error = Format::parse("[{name").err().unwrap().to_string();
+        assert!(error.contains("'{' is never closed"), "{}", error);
+        assert!(error.ends_with("\n   ^"), "{}", error);
+    }
+
+    #[test]
+    fn index_goes_stale_when_the_data_file_changes() {
+        let dir = std::env::temp_dir().join(format!("apps-helper-prompt-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (data_file, index_file) = (dir.join("apps.json"), dir.join("apps.prompt-index.json"));
+        fs::write(&data_file, "{}").unwrap();
+
+        let stamp = Stamp::of(&data_file);
+        Index::build(&data(), stamp, None).save(&index_file).unwrap();
+        assert!(Index::load_fresh(&index_file, stamp).is_some());
+
+        fs::write(&data_file, "{ }").unwrap();
+        assert!(Index::load_fresh(&index_file, Stamp::of(&data_file)).is_none());
+        assert!(Index::load_fresh(&index_file, None).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,491 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use clap::ValueEnum;
+use std::ops::Range;
+
+use crate::{App, ProfileType, normalize_name, parse_timestamp};
+
+/// A parsed `--where` expression, e.g.
+/// `tag:rust and (machine:laptop or not has:tasks) updated:<7d`.
+#[derive(Debug)]
+pub enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Term(Term),
+}
+
+#[derive(Debug)]
+pub enum Term {
+    Name(Text),
+    Alias(Text),
+    Tag(Text),
+    Repo(Text),
+    Task(Text),
+    Notes(Text),
+    Location(Text),
+    Machine(Text),
+    Type(ProfileType),
+    Has(Part),
+    Created(When),
+    Updated(When),
+}
+
+/// A value to compare text with: the whole text, or with `~` any part of it.
+/// Case and punctuation are ignored either way.
+#[derive(Debug)]
+pub struct Text {
+    value: String,
+    contains: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Part {
+    Tasks,
+    Tags,
+    Aliases,
+    Profiles,
+    Repo,
+    Notes,
+}
+
+/// A bound on a timestamp. Relative ages are turned into a point in time
+/// when parsing, so `updated:<7d` becomes "after seven days ago".
+#[derive(Debug)]
+pub struct When {
+    after: bool,
+    inclusive: bool,
+    at: DateTime<Utc>,
+}
+
+const FIELDS: &[&str] = &["name", "alias", "tag", "repo", "task", "notes", "location", "machine", "type", "has", "created", "updated"];
+const PARTS: &[&str] = &["tasks", "tags", "aliases", "profiles", "repo", "notes"];
+
+impl Query {
+    /// Parse `input`; errors point at the offending part of the expression.
+    pub fn parse(input: &str) -> Result<Query> {
+        Self::parse_at(input, Utc::now()).map_err(|e| {
+            let caret = " ".repeat(input[..e.span.start].chars().count())
+                + &"^".repeat(input[e.span.clone()].chars().count().max(1));
+            anyhow::anyhow!("Invalid --where expression: {}\n  {}\n  {}", e.message, input, caret)
+        })
+    }
+
+    fn parse_at(input: &str, now: DateTime<Utc>) -> Result<Query, ParseError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0, end: input.len(), now };
+        if parser.tokens.is_empty() {
+            return Err(ParseError::new("the expression is empty", 0..0));
+        }
+        let query = parser.parse_or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(query),
+            Some(token) if token.kind == TokenKind::RParen => Err(ParseError::new("')' has no matching '('", token.span.clone())),
+            Some(token) => Err(ParseError::new("unexpected input", token.span.clone())),
+        }
+    }
+
+    pub fn matches(&self, app: &App) -> bool {
+        match self {
+            Query::And(a, b) => a.matches(app) && b.matches(app),
+            Query::Or(a, b) => a.matches(app) || b.matches(app),
+            Query::Not(q) => !q.matches(app),
+            Query::Term(term) => term.matches(app),
+        }
+    }
+}
+
+impl Term {
+    fn matches(&self, app: &App) -> bool {
+        match self {
+            Term::Name(text) => text.matches(&app.name),
+            Term::Alias(text) => app.aliases.iter().any(|a| text.matches(a)),
+            Term::Tag(text) => app.tags.iter().any(|t| text.matches(t)),
+            Term::Repo(text) => app.github_repo.as_deref().is_some_and(|r| text.matches(r)),
+            Term::Task(text) => app.tasks.iter().any(|t| text.matches(t)),
+            Term::Notes(text) => app.profiles.iter().filter_map(|p| p.notes.as_deref()).any(|n| text.matches(n)),
+            Term::Location(text) => app.profiles.iter().any(|p| text.matches(&p.location.to_string_lossy())),
+            Term::Machine(text) => app.profiles.iter().filter_map(|p| p.machine_name.as_deref()).any(|m| text.matches(m)),
+            Term::Type(profile_type) => app.profiles.iter().any(|p| p.profile_type == *profile_type),
+            Term::Has(part) => match part {
+                Part::Tasks => !app.tasks.is_empty(),
+                Part::Tags => !app.tags.is_empty(),
+                Part::Aliases => !app.aliases.is_empty(),
+                Part::Profiles => !app.profiles.is_empty(),
+                Part::Repo => app.github_repo.is_some(),
+                Part::Notes => app.profiles.iter().any(|p| p.notes.is_some()),
+            },
+            Term::Created(when) => when.matches(&app.created_at),
+            Term::Updated(when) => when.matches(&app.updated_at),
+        }
+    }
+}
+
+impl Text {
+    fn matches(&self, text: &str) -> bool {
+        let text = normalize_name(text);
+        if self.contains { text.contains(&self.value) } else { text == self.value }
+    }
+}
+
+impl When {
+    fn matches(&self, timestamp: &str) -> bool {
+        let Ok(time) = DateTime::parse_from_rfc3339(timestamp) else {
+            return false;
+        };
+        let time = time.with_timezone(&Utc);
+        match (self.after, self.inclusive) {
+            (true, true) => time >= self.at,
+            (true, false) => time > self.at,
+            (false, true) => time <= self.at,
+            (false, false) => time < self.at,
+        }
+    }
+}
+
+struct ParseError {
+    message: String,
+    span: Range<usize>,
+}
+
+impl ParseError {
+    fn new(message: &str, span: Range<usize>) -> Self {
+        ParseError { message: message.to_string(), span }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Word(String),
+}
+
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let kind = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '!' => TokenKind::Not,
+            _ => {
+                // A word runs to whitespace or a parenthesis; quotes may
+                // wrap either
+                let mut word = String::new();
+                let mut quoted = false;
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if c != '"' {
+                        word.push(c);
+                        continue;
+                    }
+                    quoted = true;
+                    loop {
+                        match chars.next() {
+                            Some((_, '"')) => break,
+                            Some((_, c)) => word.push(c),
+                            None => return Err(ParseError::new("unterminated quote", i..input.len())),
+                        }
+                    }
+                }
+                let end = chars.peek().map_or(input.len(), |&(i, _)| i);
+                let kind = match word.to_lowercase().as_str() {
+                    "and" if !quoted => TokenKind::And,
+                    "or" if !quoted => TokenKind::Or,
+                    "not" if !quoted => TokenKind::Not,
+                    _ => TokenKind::Word(word),
+                };
+                tokens.push(Token { kind, span: start..end });
+                continue;
+            }
+        };
+        chars.next();
+        tokens.push(Token { kind, span: start..start + c.len_utf8() });
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens. `or` binds loosest, then `and`
+/// (which may be left out between terms), then `not`.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    end: usize,
+    now: DateTime<Utc>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_or(&mut self) -> Result<Query, ParseError> {
+        let mut query = self.parse_and()?;
+        while self.peek().is_some_and(|t| t.kind == TokenKind::Or) {
+            self.pos += 1;
+            query = Query::Or(Box::new(query), Box::new(self.parse_and()?));
+        }
+        Ok(query)
+    }
+
+    fn parse_and(&mut self) -> Result<Query, ParseError> {
+        let mut query = self.parse_not()?;
+        loop {
+            match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::And) => self.pos += 1,
+                Some(TokenKind::Word(_) | TokenKind::Not | TokenKind::LParen) => {}
+                _ => return Ok(query),
+            }
+            query = Query::And(Box::new(query), Box::new(self.parse_not()?));
+        }
+    }
+
+    fn parse_not(&mut self) -> Result<Query, ParseError> {
+        if self.peek().is_some_and(|t| t.kind == TokenKind::Not) {
+            self.pos += 1;
+            return Ok(Query::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<Query, ParseError> {
+        let Some(token) = self.tokens.get(self.pos) else {
+            return Err(ParseError::new("the expression ends early; expected a filter", self.end..self.end));
+        };
+        let span = token.span.clone();
+        self.pos += 1;
+        match &token.kind {
+            TokenKind::Word(word) => Ok(Query::Term(parse_term(word, span, self.now)?)),
+            TokenKind::LParen => {
+                let query = self.parse_or()?;
+                match self.peek() {
+                    Some(t) if t.kind == TokenKind::RParen => {
+                        self.pos += 1;
+                        Ok(query)
+                    }
+                    _ => Err(ParseError::new("'(' is never closed", span)),
+                }
+            }
+            TokenKind::RParen => Err(ParseError::new("expected a filter before ')'", span)),
+            TokenKind::And => Err(ParseError::new("expected a filter before 'and'", span)),
+            TokenKind::Or => Err(ParseError::new("expected a filter before 'or'", span)),
+            TokenKind::Not => unreachable!("handled by parse_not"),
+        }
+    }
+}
+
+fn parse_term(word: &str, span: Range<usize>, now: DateTime<Utc>) -> Result<Term, ParseError> {
+    // A bare word matches part of the name
+    let Some((field, value)) = word.split_once(':') else {
+        return Ok(Term::Name(parse_text(&format!("~{}", word), span)?));
+    };
+    let value_span = span.start + field.len() + 1..span.end;
+    if value.is_empty() {
+        return Err(ParseError::new(&format!("'{}:' needs a value", field), span));
+    }
+
+    let term = match field.to_lowercase().as_str() {
+        "name" => Term::Name(parse_text(value, value_span)?),
+        "alias" => Term::Alias(parse_text(value, value_span)?),
+        "tag" => Term::Tag(parse_text(value, value_span)?),
+        "repo" => Term::Repo(parse_text(value, value_span)?),
+        "task" => Term::Task(parse_text(value, value_span)?),
+        "notes" => Term::Notes(parse_text(value, value_span)?),
+        "location" => Term::Location(parse_text(value, value_span)?),
+        "machine" => Term::Machine(parse_text(value, value_span)?),
+        "type" => Term::Type(ProfileType::from_str(value, true).map_err(|_| {
+            ParseError::new(&format!("unknown profile type '{}' (expected dev, installed, binary or config)", value), value_span)
+        })?),
+        "has" => Term::Has(parse_part(value, value_span)?),
+        "created" => Term::Created(parse_when(value, value_span, now)?),
+        "updated" => Term::Updated(parse_when(value, value_span, now)?),
+        _ => {
+            let message = format!("unknown field '{}' (expected one of: {})", field, FIELDS.join(", "));
+            return Err(ParseError::new(&message, span.start..span.start + field.len()));
+        }
+    };
+    Ok(term)
+}
+
+fn parse_text(value: &str, span: Range<usize>) -> Result<Text, ParseError> {
+    let (contains, value) = match value.strip_prefix('~') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let value = normalize_name(value);
+    if value.is_empty() {
+        return Err(ParseError::new("the value must contain at least one letter or digit", span));
+    }
+    Ok(Text { value, contains })
+}
+
+fn parse_part(value: &str, span: Range<usize>) -> Result<Part, ParseError> {
+    let part = match value.to_lowercase().as_str() {
+        "tasks" | "task" => Part::Tasks,
+        "tags" | "tag" => Part::Tags,
+        "aliases" | "alias" => Part::Aliases,
+        "profiles" | "profile" => Part::Profiles,
+        "repo" => Part::Repo,
+        "notes" => Part::Notes,
+        _ => {
+            let message = format!("unknown value for has: '{}' (expected one of: {})", value, PARTS.join(", "));
+            return Err(ParseError::new(&message, span));
+        }
+    };
+    Ok(part)
+}
+
+/// `<7d`, `>=6m`, `<2024-01-01`. Ages count back from `now`, so "less
+/// than seven days ago" is "after seven days ago".
+fn parse_when(value: &str, span: Range<usize>, now: DateTime<Utc>) -> Result<When, ParseError> {
+    let (op, rest) = ["<=", ">=", "<", ">"]
+        .iter()
+        .find_map(|op| value.strip_prefix(op).map(|rest| (*op, rest)))
+        .ok_or_else(|| ParseError::new("expected <, >, <= or >= followed by an age like 7d or a date", span.clone()))?;
+    let inclusive = op.len() == 2;
+    let later = op.starts_with('>');
+    let rest_span = span.start + op.len()..span.end;
+
+    if let Some(age) = parse_age(rest, rest_span.clone())? {
+        let at = now
+            .checked_sub_signed(age)
+            .ok_or_else(|| ParseError::new(&format!("'{}' reaches back too far", rest), rest_span.clone()))?;
+        // An older age is an earlier time
+        return Ok(When { after: !later, inclusive, at });
+    }
+    match parse_timestamp(rest) {
+        Some(at) => Ok(When { after: later, inclusive, at }),
+        None => Err(ParseError::new(
+            &format!("'{}' is neither an age (12h, 7d, 2w, 6m, 1y) nor a date (YYYY-MM-DD)", rest),
+            rest_span,
+        )),
+    }
+}
+
+/// None when `value` isn't shaped like an age, so it can be tried as a date.
+fn parse_age(value: &str, span: Range<usize>) -> Result<Option<Duration>, ParseError> {
+    let Some(split) = value.find(|c: char| !c.is_ascii_digit()).filter(|&i| i > 0) else {
+        return Ok(None);
+    };
+    let (number, unit) = value.split_at(split);
+    if !["h", "d", "w", "m", "y"].contains(&unit) {
+        return Ok(None);
+    }
+    let age = number.parse::<i64>().ok().and_then(|number| match unit {
+        "h" => Duration::try_hours(number),
+        "d" => Duration::try_days(number),
+        "w" => Duration::try_weeks(number),
+        "m" => number.checked_mul(30).and_then(Duration::try_days),
+        _ => number.checked_mul(365).and_then(Duration::try_days),
+    });
+    age.map(Some).ok_or_else(|| ParseError::new(&format!("'{}' is too long an age", value), span))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-06-15T12:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    fn parse(input: &str) -> Query {
+        match Query::parse_at(input, now()) {
+            Ok(query) => query,
+            Err(e) => panic!("{} failed to parse: {} at {:?}", input, e.message, e.span),
+        }
+    }
+
+    /// The message and the part of `input` the error points at.
+    fn error(input: &str) -> (String, &str) {
+        match Query::parse_at(input, now()) {
+            Ok(query) => panic!("{} parsed as {:?}", input, query),
+            Err(e) => (e.message, &input[e.span]),
+        }
+    }
+
+    /// The query with its grouping made explicit, bare words only.
+    fn shape(query: &Query) -> String {
+        match query {
+            Query::And(a, b) => format!("({} and {})", shape(a), shape(b)),
+            Query::Or(a, b) => format!("({} or {})", shape(a), shape(b)),
+            Query::Not(q) => format!("not {}", shape(q)),
+            Query::Term(Term::Name(text)) => text.value.clone(),
+            Query::Term(term) => format!("{:?}", term),
+        }
+    }
+
+    fn when(input: &str) -> (bool, bool, DateTime<Utc>) {
+        match parse(input) {
+            Query::Term(Term::Updated(when) | Term::Created(when)) => (when.after, when.inclusive, when.at),
+            query => panic!("{} parsed as {:?}", input, query),
+        }
+    }
+
+    #[test]
+    fn ages_count_back_from_now() {
+        assert_eq!(when("updated:<7d"), (true, false, now() - Duration::days(7)));
+        assert_eq!(when("updated:>=2w"), (false, true, now() - Duration::weeks(2)));
+        assert_eq!(when("created:>12h"), (false, false, now() - Duration::hours(12)));
+        assert_eq!(when("created:<=6m"), (true, true, now() - Duration::days(180)));
+        assert_eq!(when("updated:<1y"), (true, false, now() - Duration::days(365)));
+    }
+
+    #[test]
+    fn dates_are_points_in_time() {
+        let (after, inclusive, at) = when("updated:>2024-01-01T08:30:00Z");
+        assert!(after && !inclusive);
+        assert_eq!(at.to_rfc3339(), "2024-01-01T08:30:00+00:00");
+        assert!(!when("created:<=2024-01-01").0);
+    }
+
+    #[test]
+    fn ages_too_long_for_the_calendar_are_errors() {
+        assert_eq!(error("updated:<999999999999d"), ("'999999999999d' is too long an age".to_string(), "999999999999d"));
+        assert_eq!(error("updated:<99999999999999999999y").1, "99999999999999999999y");
+        assert_eq!(error("tag:x created:>9223372036854775807m").1, "9223372036854775807m");
+        assert_eq!(error("updated:<100000000d"), ("'100000000d' reaches back too far".to_string(), "100000000d"));
+    }
+
+    #[test]
+    fn or_binds_loosest_then_and_then_not() {
+        assert_eq!(shape(&parse("a or b c")), "(a or (b and c))");
+        assert_eq!(shape(&parse("a and b or c")), "((a and b) or c)");
+        assert_eq!(shape(&parse("not a b")), "(not a and b)");
+        assert_eq!(shape(&parse("!a or not not b")), "(not a or not not b)");
+        assert_eq!(shape(&parse("a (b or c)")), "(a and (b or c))");
+        assert_eq!(shape(&parse("\"or\" x")), "(or and x)");
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_part() {
+        assert_eq!(error(""), ("the expression is empty".to_string(), ""));
+        assert_eq!(error("a (b or c").1, "(");
+        assert_eq!(error("a b)").1, ")");
+        assert_eq!(error("a or").0, "the expression ends early; expected a filter");
+        assert_eq!(error("and a").1, "and");
+        assert_eq!(error("tag:x colour:red").1, "colour");
+        assert_eq!(error("type:laptop").1, "laptop");
+        assert_eq!(error("has:cats").1, "cats");
+        assert_eq!(error("tag:").1, "tag:");
+        assert_eq!(error("updated:7d").1, "7d");
+        assert_eq!(error("updated:<soon").1, "soon");
+        assert_eq!(error("name:\"open").1, "\"open");
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use crate::{App, AppsData, matcher};
+
+/// Where in an app a search hit was found.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Name,
+    Alias,
+    Tag,
+    Repo,
+    Task,
+    Notes,
+    Location,
+}
+
+impl Field {
+    /// How much a hit in this field counts towards an app's rank
+    fn weight(self) -> u32 {
+        match self {
+            Field::Name => 400,
+            Field::Alias => 300,
+            Field::Tag => 200,
+            Field::Repo => 150,
+            Field::Task => 100,
+            Field::Notes => 80,
+            Field::Location => 50,
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Field::Name => "name",
+            Field::Alias => "alias",
+            Field::Tag => "tag",
+            Field::Repo => "repo",
+            Field::Task => "task",
+            Field::Notes => "notes",
+            Field::Location => "location",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// One field value that matched, with the byte ranges of the matches.
+pub struct Hit {
+    pub field: Field,
+    pub text: String,
+    pub ranges: Vec<Range<usize>>,
+}
+
+pub struct Found<'a> {
+    pub key: &'a str,
+    pub score: u32,
+    pub hits: Vec<Hit>,
+}
+
+// Characters of context kept on each side of the first match in a snippet
+const SNIPPET_CONTEXT: usize = 30;
+
+/// The pattern for `query`: plain text ignoring case, or a regex used as written.
+pub fn pattern(query: &str, regex: bool) -> Result<Regex> {
+    let source = if regex { query.to_string() } else { regex::escape(query) };
+    RegexBuilder::new(&source)
+        .case_insensitive(!regex)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Invalid regex '{}': {}", query, e))
+}
+
+/// Every app with a field matching `pattern`, best first. With
+/// `fuzzy_query`, names that only match fuzzily are included too.
+pub fn search<'a>(data: &'a AppsData, pattern: &Regex, fuzzy_query: Option<&str>) -> Vec<Found<'a>> {
+    let fuzzy: HashMap<&str, u32> = fuzzy_query
+        .map(|query| matcher::rank(data, query).into_iter().map(|m| (m.key, m.score)).collect())
+        .unwrap_or_default();
+
+    let mut results: Vec<Found> = data
+        .apps
+        .iter()
+        .filter_map(|(key, app)| {
+            let mut hits = find_hits(app, pattern);
+            let fuzzy_score = fuzzy.get(key.as_str()).copied();
+            if hits.is_empty() && fuzzy_score.is_none() {
+                return None;
+            }
+            let score = fuzzy_score.unwrap_or(0) + hits.iter().map(|h| h.field.weight()).sum::<u32>();
+            if fuzzy_score.is_some() && !hits.iter().any(|h| matches!(h.field, Field::Name | Field::Alias)) {
+                // Matched by spelling rather than text; show the name unmarked
+                hits.insert(0, Hit { field: Field::Name, text: app.name.clone(), ranges: Vec::new() });
+            }
+            Some(Found { key, score, hits })
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.key.cmp(b.key)));
+    results
+}
+
+fn find_hits(app: &App, pattern: &Regex) -> Vec<Hit> {
+    let mut fields = vec![(Field::Name, app.name.clone())];
+    fields.extend(app.aliases.iter().map(|a| (Field::Alias, a.clone())));
+    fields.extend(app.tags.iter().map(|t| (Field::Tag, t.clone())));
+    fields.extend(app.github_repo.iter().map(|r| (Field::Repo, r.clone())));
+    fields.extend(app.tasks.iter().map(|t| (Field::Task, t.clone())));
+    for profile in &app.profiles {
+        fields.push((Field::Location, profile.location.display().to_string()));
+        fields.extend(profile.notes.iter().map(|n| (Field::Notes, n.clone())));
+    }
+
+    fields
+        .into_iter()
+        .filter_map(|(field, text)| {
+            let ranges: Vec<Range<usize>> = pattern.find_iter(&text).map(|m| m.range()).filter(|r| !r.is_empty()).collect();
+            (!ranges.is_empty()).then_some(Hit { field, text, ranges })
+        })
+        .collect()
+}
+
+impl Hit {
+    /// The text around the first match, with matches set in bold red when
+    /// `highlight` is on.
+    pub fn snippet(&self, highlight: bool) -> String {
+        let text = self.text.as_str();
+        let (start, end) = match self.ranges.first() {
+            Some(first) => (
+                char_boundary_before(text, first.start, SNIPPET_CONTEXT),
+                char_boundary_after(text, first.end, SNIPPET_CONTEXT),
+            ),
+            None => (0, text.len()),
+        };
+
+        let mut snippet = String::new();
+        if start > 0 {
+            snippet.push('…');
+        }
+        let mut pos = start;
+        for range in self.ranges.iter().filter(|r| r.start >= start && r.end <= end) {
+            snippet.push_str(&text[pos..range.start]);
+            if highlight {
+                snippet.push_str(&format!("\x1b[1;31m{}\x1b[0m", &text[range.clone()]));
+            } else {
+                snippet.push_str(&text[range.clone()]);
+            }
+            pos = range.end;
+        }
+        snippet.push_str(&text[pos..end]);
+        if end < text.len() {
+            snippet.push('…');
+        }
+        snippet
+    }
+}
+
+/// Byte offset `chars` characters before `index`, or the start of `text`.
+fn char_boundary_before(text: &str, index: usize, chars: usize) -> usize {
+    text[..index].char_indices().rev().nth(chars.saturating_sub(1)).map_or(0, |(i, _)| i)
+}
+
+/// Byte offset `chars` characters after `index`, or the end of `text`.
+fn char_boundary_after(text: &str, index: usize, chars: usize) -> usize {
+    text[index..].char_indices().nth(chars).map_or(text.len(), |(i, _)| index + i)
+}
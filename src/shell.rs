@@ -0,0 +1,61 @@
+use clap::ValueEnum;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// A wrapper function named `name` that runs `exe` for everything except
+/// `cd`, which it turns into a `cd` to the path `exe path` prints. A child
+/// process can't change its parent shell's directory, so the shell has to.
+/// Without `exe` it runs whichever apps-helper is on the PATH, so the
+/// script keeps working when the binary is upgraded or moved.
+pub fn init_script(shell: Shell, name: &str, exe: Option<&Path>) -> String {
+    let exe = exe.map(|exe| exe.to_string_lossy());
+    match shell {
+        Shell::Bash | Shell::Zsh => format!(
+            r#"# apps-helper shell integration; add to your shell's rc file:
+#   eval "$(apps-helper shell-init {shell})"
+{name}() {{
+    if [ "$1" = "cd" ]; then
+        shift
+        local dir
+        dir="$({exe} path "$@")" || return
+        builtin cd -- "$dir"
+    else
+        {exe} "$@"
+    fi
+}}
+"#,
+            shell = if shell == Shell::Bash { "bash" } else { "zsh" },
+            name = name,
+            exe = exe.map_or_else(|| "command apps-helper".to_string(), |exe| quote_posix(&exe)),
+        ),
+        Shell::Fish => format!(
+            r#"# apps-helper shell integration; add to ~/.config/fish/config.fish:
+#   apps-helper shell-init fish | source
+function {name} --wraps apps-helper
+    if test "$argv[1]" = cd
+        set -l dir ({exe} path $argv[2..-1]); or return
+        builtin cd -- $dir
+    else
+        {exe} $argv
+    end
+end
+"#,
+            name = name,
+            exe = exe.map_or_else(|| "command apps-helper".to_string(), |exe| quote_fish(&exe)),
+        ),
+    }
+}
+
+fn quote_posix(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+fn quote_fish(text: &str) -> String {
+    format!("'{}'", text.replace('\\', r"\\").replace('\'', r"\'"))
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Store, write_atomic};
+use crate::AppsData;
+use crate::migrations::{self, MigrationReport};
+
+/// The whole registry as one pretty-printed JSON document.
+pub struct JsonStore {
+    path: PathBuf,
+}
+
+/// Machine-local state kept beside the document (`apps.json.seq`) so the
+/// document itself stays the same on every machine that shares it.
+#[derive(Serialize, Deserialize)]
+struct SeqFile {
+    event_seq: u64,
+    /// Hash of the document as last written by us; a mismatch means it was
+    /// changed from outside
+    hash: String,
+}
+
+impl JsonStore {
+    pub fn new(path: PathBuf) -> JsonStore {
+        JsonStore { path }
+    }
+
+    fn seq_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".seq");
+        self.path.with_file_name(name)
+    }
+
+    fn read_event_seq(&self, content: &str) -> Option<u64> {
+        let seq: SeqFile = serde_json::from_slice(&fs::read(self.seq_path()).ok()?).ok()?;
+        (seq.hash == content_hash(content.as_bytes())).then_some(seq.event_seq)
+    }
+}
+
+impl Store for JsonStore {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<(AppsData, MigrationReport)> {
+        let content = fs::read_to_string(&self.path)?;
+        let (mut data, report) = parse(&content).map_err(|e| {
+            anyhow::anyhow!("{} is invalid: {}\nRun `apps-helper validate` to list every problem.", self.path.display(), e)
+        })?;
+        data.event_seq = self.read_event_seq(&content);
+        Ok((data, report))
+    }
+
+    fn save(&self, data: &AppsData) -> Result<()> {
+        let content = serde_json::to_string_pretty(data)?;
+        // Write to a temp file and rename it into place so a crash never truncates the registry
+        write_atomic(&self.path, content.as_bytes())?;
+        
+        match data.event_seq {
+            Some(event_seq) => {
+                let seq = SeqFile { event_seq, hash: content_hash(content.as_bytes()) };
+                write_atomic(&self.seq_path(), &serde_json::to_vec(&seq)?)
+            }
+            None => match fs::remove_file(self.seq_path()) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            },
+        }
+    }
+
+    fn snapshot(&self) -> Result<Option<Vec<u8>>> {
+        if !self.exists() {
+            return Ok(None);
+        }
+        Ok(Some(fs::read(&self.path)?))
+    }
+}
+
+/// Parse an apps.json document of any supported schema version.
+pub fn parse(content: &str) -> Result<(AppsData, MigrationReport)> {
+    let mut document: serde_json::Value = serde_json::from_str(content)?;
+    let report = migrations::migrate(&mut document)?;
+    let data: AppsData = serde_json::from_value(document)?;
+    Ok((data, report))
+}
+
+/// FNV-1a, stable across builds unlike the std hasher.
+fn content_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}", hash)
+}
@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use std::cell::Cell;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const DEFAULT_LOCK_TIMEOUT_SECS: u64 = 10;
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+thread_local! {
+    // Number of live DataLock guards in this process. The OS lock is only
+    // taken by the outermost guard so nested load/save calls don't deadlock.
+    static LOCK_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Advisory lock on the data file, held for a whole read-modify-write cycle.
+pub struct DataLock {
+    file: Option<File>,
+}
+
+impl DataLock {
+    pub fn acquire(lock_path: &Path) -> Result<DataLock> {
+        if LOCK_DEPTH.with(|depth| depth.get()) > 0 {
+            LOCK_DEPTH.with(|depth| depth.set(depth.get() + 1));
+            return Ok(DataLock { file: None });
+        }
+
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path)
+            .with_context(|| format!("Failed to open lock file {}", lock_path.display()))?;
+
+        let timeout = lock_timeout();
+        let started = Instant::now();
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => break,
+                Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => {
+                    if started.elapsed() >= timeout {
+                        return Err(anyhow::anyhow!(
+                            "Timed out after {}s waiting for another apps-helper process to release {}",
+                            timeout.as_secs(),
+                            lock_path.display()
+                        ));
+                    }
+                    std::thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to lock {}", lock_path.display()));
+                }
+            }
+        }
+
+        LOCK_DEPTH.with(|depth| depth.set(1));
+        Ok(DataLock { file: Some(file) })
+    }
+}
+
+impl Drop for DataLock {
+    fn drop(&mut self) {
+        LOCK_DEPTH.with(|depth| depth.set(depth.get().saturating_sub(1)));
+        if let Some(file) = &self.file {
+            let _ = FileExt::unlock(file);
+        }
+    }
+}
+
+fn lock_timeout() -> Duration {
+    let secs = std::env::var("APPS_HELPER_LOCK_TIMEOUT")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_LOCK_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Replace `path` with `content` so that readers see either the old or the
+/// new file, never a truncated one.
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(parent)?;
+
+    let tmp_path = temp_path_for(path);
+    let result = (|| -> Result<()> {
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(content)?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result.with_context(|| format!("Failed to write {}", path.display()))?;
+
+    // Persist the rename itself
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("data");
+    path.with_file_name(format!(".{}.tmp-{}", file_name, std::process::id()))
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::AppsData;
+use crate::migrations::MigrationReport;
+
+pub mod json;
+mod lock;
+mod sqlite;
+
+pub use lock::{DataLock, write_atomic};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ValueEnum, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+impl Backend {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Backend::Json => "apps.json",
+            Backend::Sqlite => "apps.db",
+        }
+    }
+
+    /// Pick the backend from a data file's extension; anything that isn't a
+    /// SQLite database is treated as JSON.
+    pub fn for_path(path: &Path) -> Backend {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("db" | "sqlite" | "sqlite3") => Backend::Sqlite,
+            _ => Backend::Json,
+        }
+    }
+}
+
+/// Where `AppsData` is persisted. Callers hold a `DataLock` around
+/// load/save cycles; implementations only need to make `save` atomic.
+pub trait Store {
+    fn path(&self) -> &Path;
+
+    fn exists(&self) -> bool {
+        self.path().exists()
+    }
+
+    /// Read the data, upgrading older schemas in memory. The report says
+    /// whether an upgrade happened so the caller can write it back.
+    fn load(&self) -> Result<(AppsData, MigrationReport)>;
+
+    fn save(&self, data: &AppsData) -> Result<()>;
+
+    /// The current state as JSON, used for backups. `None` if nothing is stored yet.
+    fn snapshot(&self) -> Result<Option<Vec<u8>>> {
+        if !self.exists() {
+            return Ok(None);
+        }
+        let (data, _) = self.load()?;
+        Ok(Some(serde_json::to_vec_pretty(&data)?))
+    }
+}
+
+pub fn open(path: &Path) -> Box<dyn Store> {
+    match Backend::for_path(path) {
+        Backend::Json => Box::new(json::JsonStore::new(path.to_path_buf())),
+        Backend::Sqlite => Box::new(sqlite::SqliteStore::new(path.to_path_buf())),
+    }
+}
+
+/// Path of the `backend` file that sits next to `data_file`, with the same
+/// stem so it keeps the registry's log, backups and views.
+pub fn sibling_path(data_file: &Path, backend: Backend) -> PathBuf {
+    data_file.with_extension(Path::new(backend.file_name()).extension().unwrap_or_default())
+}
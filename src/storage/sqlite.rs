@@ -0,0 +1,240 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::Store;
+use crate::migrations::{self, MigrationReport};
+use crate::{App, AppProfile, AppsData};
+
+// Table layout, one entry per `PRAGMA user_version`
+const SCHEMA: &[&str] = &["
+    CREATE TABLE meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE apps (
+        key TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        tags TEXT NOT NULL,
+        github_repo TEXT,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE profiles (
+        app_key TEXT NOT NULL REFERENCES apps(key) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        profile_type TEXT NOT NULL,
+        location TEXT NOT NULL,
+        machine_name TEXT,
+        notes TEXT,
+        active INTEGER NOT NULL,
+        PRIMARY KEY (app_key, position)
+    );
+    CREATE TABLE tasks (
+        app_key TEXT NOT NULL REFERENCES apps(key) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        task TEXT NOT NULL,
+        PRIMARY KEY (app_key, position)
+    );
+", "
+    ALTER TABLE apps ADD COLUMN aliases TEXT NOT NULL DEFAULT '[]';
+", "
+    ALTER TABLE apps ADD COLUMN opener TEXT;
+", "
+    ALTER TABLE apps ADD COLUMN layout TEXT NOT NULL DEFAULT '[]';
+"];
+
+/// Apps, profiles and tasks in their own tables of an embedded SQLite database.
+pub struct SqliteStore {
+    path: PathBuf,
+}
+
+impl SqliteStore {
+    pub fn new(path: PathBuf) -> SqliteStore {
+        SqliteStore { path }
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut conn = Connection::open(&self.path)
+            .with_context(|| format!("Failed to open database {}", self.path.display()))?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let version = usize::try_from(version).unwrap_or(usize::MAX);
+        if version > SCHEMA.len() {
+            return Err(anyhow::anyhow!(
+                "Database {} was created by a newer apps-helper (table version {})",
+                self.path.display(),
+                version
+            ));
+        }
+        if version < SCHEMA.len() {
+            let tx = conn.transaction()?;
+            for statements in &SCHEMA[version..] {
+                tx.execute_batch(statements)?;
+            }
+            tx.pragma_update(None, "user_version", SCHEMA.len() as i64)?;
+            tx.commit()?;
+        }
+        Ok(conn)
+    }
+}
+
+impl Store for SqliteStore {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<(AppsData, MigrationReport)> {
+        let conn = self.connect()?;
+        let apps = read_apps(&conn)?;
+        let schema_version = read_meta(&conn, "schema_version")?.unwrap_or(migrations::CURRENT_SCHEMA_VERSION);
+        let event_seq = read_meta(&conn, "event_seq")?;
+
+        let data = AppsData { schema_version, event_seq, apps };
+        Ok((data, MigrationReport::up_to_date(schema_version)))
+    }
+
+    fn save(&self, data: &AppsData) -> Result<()> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+
+        // Only rewrite the apps that changed, so saving one edit costs a
+        // few rows rather than the whole registry
+        let stored = read_apps(&tx)?;
+        for key in stored.keys().filter(|key| !data.apps.contains_key(*key)) {
+            // Profiles and tasks go with it through ON DELETE CASCADE
+            tx.execute("DELETE FROM apps WHERE key = ?1", params![key])?;
+        }
+        for (key, app) in &data.apps {
+            if stored.get(key) != Some(app) {
+                write_app(&tx, key, app)?;
+            }
+        }
+
+        if read_meta::<u32>(&tx, "schema_version")? != Some(data.schema_version) {
+            tx.execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', ?1)",
+                params![data.schema_version.to_string()],
+            )?;
+        }
+        match data.event_seq {
+            Some(seq) => tx.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('event_seq', ?1)", params![seq.to_string()])?,
+            None => tx.execute("DELETE FROM meta WHERE key = 'event_seq'", [])?,
+        };
+
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn read_apps(conn: &Connection) -> Result<HashMap<String, App>> {
+    let mut profiles: HashMap<String, Vec<AppProfile>> = HashMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT app_key, profile_type, location, machine_name, notes, active FROM profiles ORDER BY app_key, position",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let profile_type: String = row.get(1)?;
+        let location: String = row.get(2)?;
+        profiles.entry(row.get(0)?).or_default().push(AppProfile {
+            profile_type: serde_json::from_value(serde_json::Value::String(profile_type))?,
+            location: PathBuf::from(location),
+            machine_name: row.get(3)?,
+            notes: row.get(4)?,
+            active: row.get(5)?,
+        });
+    }
+
+    let mut tasks: HashMap<String, Vec<String>> = HashMap::new();
+    let mut stmt = conn.prepare("SELECT app_key, task FROM tasks ORDER BY app_key, position")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        tasks.entry(row.get(0)?).or_default().push(row.get(1)?);
+    }
+
+    let mut apps = HashMap::new();
+    let mut stmt = conn.prepare("SELECT key, name, tags, github_repo, created_at, updated_at, aliases, opener, layout FROM apps")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let key: String = row.get(0)?;
+        let tags: String = row.get(2)?;
+        let aliases: String = row.get(6)?;
+        let opener: Option<String> = row.get(7)?;
+        let layout: String = row.get(8)?;
+        let app = App {
+            name: row.get(1)?,
+            profiles: profiles.remove(&key).unwrap_or_default(),
+            tags: serde_json::from_str(&tags)?,
+            aliases: serde_json::from_str(&aliases)?,
+            github_repo: row.get(3)?,
+            opener: opener.map(|o| serde_json::from_value(serde_json::Value::String(o))).transpose()?,
+            layout: serde_json::from_str(&layout)?,
+            tasks: tasks.remove(&key).unwrap_or_default(),
+            created_at: row.get(4)?,
+            updated_at: row.get(5)?,
+        };
+        apps.insert(key, app);
+    }
+    Ok(apps)
+}
+
+/// Replace the row of app `key` and all of its profiles and tasks.
+fn write_app(conn: &Connection, key: &str, app: &App) -> Result<()> {
+    conn.execute("DELETE FROM profiles WHERE app_key = ?1", params![key])?;
+    conn.execute("DELETE FROM tasks WHERE app_key = ?1", params![key])?;
+
+    let opener = app.opener.map(serde_json::to_value).transpose()?;
+    conn.prepare_cached(
+        "INSERT INTO apps (key, name, tags, github_repo, created_at, updated_at, aliases, opener, layout) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT (key) DO UPDATE SET name = excluded.name, tags = excluded.tags, github_repo = excluded.github_repo,
+             created_at = excluded.created_at, updated_at = excluded.updated_at, aliases = excluded.aliases,
+             opener = excluded.opener, layout = excluded.layout",
+    )?
+    .execute(params![
+        key,
+        app.name,
+        serde_json::to_string(&app.tags)?,
+        app.github_repo,
+        app.created_at,
+        app.updated_at,
+        serde_json::to_string(&app.aliases)?,
+        opener.as_ref().and_then(|o| o.as_str()),
+        serde_json::to_string(&app.layout)?,
+    ])?;
+
+    let mut insert_profile = conn.prepare_cached(
+        "INSERT INTO profiles (app_key, position, profile_type, location, machine_name, notes, active) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for (position, profile) in (0i64..).zip(&app.profiles) {
+        let profile_type = serde_json::to_value(profile.profile_type)?;
+        let location = profile.location.to_str().ok_or_else(|| {
+            anyhow::anyhow!("Profile location is not valid UTF-8: {}", profile.location.display())
+        })?;
+        insert_profile.execute(params![
+            key,
+            position,
+            profile_type.as_str(),
+            location,
+            profile.machine_name,
+            profile.notes,
+            profile.active,
+        ])?;
+    }
+    let mut insert_task = conn.prepare_cached("INSERT INTO tasks (app_key, position, task) VALUES (?1, ?2, ?3)")?;
+    for (position, task) in (0i64..).zip(&app.tasks) {
+        insert_task.execute(params![key, position, task])?;
+    }
+    Ok(())
+}
+
+fn read_meta<T: std::str::FromStr>(conn: &Connection, key: &str) -> Result<Option<T>> {
+    let value: Option<String> = conn
+        .query_row("SELECT value FROM meta WHERE key = ?1", params![key], |row| row.get(0))
+        .optional()?;
+    Ok(value.and_then(|v| v.parse().ok()))
+}
@@ -0,0 +1,147 @@
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::ProfileType;
+
+/// A tmux window of an app's layout, created in order by `tmux`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct Window {
+    pub name: String,
+    pub panes: Vec<Pane>,
+}
+
+/// A pane and what to run in it; without a command it's just a shell.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct Pane {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    // Profile whose location the pane starts in; the Dev profile if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile_type: Option<ProfileType>,
+}
+
+impl fmt::Display for Window {
+    /// `dev: cargo watch | shell @installed`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:", self.name)?;
+        for (i, pane) in self.panes.iter().enumerate() {
+            let separator = if i == 0 { " " } else { " | " };
+            write!(f, "{}{}", separator, pane.command.as_deref().unwrap_or("shell"))?;
+            if let Some(profile_type) = pane.profile_type {
+                write!(f, " @{}", format!("{:?}", profile_type).to_lowercase())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A window with its panes' directories and commands worked out.
+pub struct Planned {
+    pub name: String,
+    pub panes: Vec<(PathBuf, Option<String>)>,
+}
+
+/// tmux doesn't allow `.` or `:` in session names.
+pub fn session_name(app_name: &str) -> String {
+    app_name.replace(['.', ':'], "_")
+}
+
+/// Fill in a pane command. It's typed into the pane's shell, so `{path}`
+/// and `{name}` are quoted while `{editor}` goes in as is.
+pub fn pane_command(template: &str, path: &Path, name: &str, editor: &str) -> String {
+    let quote = |text: &str| shlex::try_quote(text).map_or_else(|_| text.to_string(), |q| q.into_owned());
+    template
+        .replace("{path}", &quote(&path.to_string_lossy()))
+        .replace("{name}", &quote(name))
+        .replace("{editor}", editor)
+}
+
+pub fn has_session(session: &str) -> Result<bool> {
+    let output = Command::new("tmux")
+        .args(["has-session", "-t", &format!("={}", session)])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run tmux: {}. Is it installed?", e))?;
+    Ok(output.status.success())
+}
+
+/// Start a detached session in `dir` and build `windows` in it; with no
+/// windows it's a single shell.
+pub fn create(session: &str, dir: &Path, windows: &[Planned]) -> Result<()> {
+    let mut started = false;
+    let built = build(session, dir, windows, &mut started);
+    if built.is_err() && started {
+        // Don't leave a half-built session behind for the next run to attach to
+        let _ = tmux(&["kill-session", "-t", &format!("={}", session)]);
+    }
+    built
+}
+
+/// Sets `started` once the session exists.
+fn build(session: &str, dir: &Path, windows: &[Planned], started: &mut bool) -> Result<()> {
+    if windows.is_empty() {
+        tmux(&["new-session", "-d", "-s", session, "-c", &dir.to_string_lossy()])?;
+        return Ok(());
+    }
+
+    let mut first_pane = None;
+    for window in windows {
+        let mut window_pane: Option<String> = None;
+        for (dir, command) in &window.panes {
+            let dir = dir.to_string_lossy();
+            let pane = match (&first_pane, &window_pane) {
+                (None, _) => {
+                    let pane = tmux(&["new-session", "-d", "-P", "-F", "#{pane_id}", "-s", session, "-n", &window.name, "-c", &dir])?;
+                    *started = true;
+                    pane
+                }
+                (Some(_), None) => tmux(&["new-window", "-d", "-P", "-F", "#{pane_id}", "-t", &format!("={}:", session), "-n", &window.name, "-c", &dir])?,
+                (_, Some(target)) => {
+                    let pane = tmux(&["split-window", "-d", "-P", "-F", "#{pane_id}", "-t", target, "-c", &dir])?;
+                    tmux(&["select-layout", "-t", target, "tiled"])?;
+                    pane
+                }
+            };
+            if let Some(command) = command {
+                tmux(&["send-keys", "-t", &pane, "-l", command])?;
+                tmux(&["send-keys", "-t", &pane, "Enter"])?;
+            }
+            first_pane.get_or_insert_with(|| pane.clone());
+            window_pane.get_or_insert(pane);
+        }
+    }
+    if let Some(pane) = first_pane {
+        tmux(&["select-window", "-t", &pane])?;
+    }
+    Ok(())
+}
+
+/// Attach to `session`, or switch to it when already inside tmux.
+pub fn attach(session: &str) -> Result<()> {
+    let target = format!("={}", session);
+    let args = if std::env::var_os("TMUX").is_some() {
+        ["switch-client", "-t", &target]
+    } else {
+        ["attach-session", "-t", &target]
+    };
+    let status = Command::new("tmux").args(args).status().map_err(|e| anyhow::anyhow!("Failed to run tmux: {}", e))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("tmux {} exited with {}", args[0], status));
+    }
+    Ok(())
+}
+
+/// Run a tmux command, returning what it printed.
+fn tmux(args: &[&str]) -> Result<String> {
+    let output = Command::new("tmux")
+        .args(args)
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run tmux: {}. Is it installed?", e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("tmux {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
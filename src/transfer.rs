@@ -0,0 +1,286 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::{App, AppProfile, AppsData, Opener, ProfileType, validate};
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+    Csv,
+}
+
+impl Format {
+    pub fn for_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// The exported form of the registry: apps as a list sorted by name, since
+/// the map keys in apps.json only repeat each app's name.
+#[derive(Serialize, Deserialize)]
+struct Document {
+    apps: Vec<App>,
+}
+
+/// One CSV row: an app's fields repeated for each of its profiles. Apps
+/// without profiles get a single row with the profile columns left empty.
+#[derive(Serialize, Deserialize)]
+struct Row {
+    name: String,
+    tags: String,
+    #[serde(default)]
+    aliases: String,
+    github_repo: Option<String>,
+    #[serde(default)]
+    opener: Option<Opener>,
+    // The tmux layout as JSON, empty when there is none
+    #[serde(default)]
+    layout: String,
+    tasks: String,
+    created_at: String,
+    updated_at: String,
+    profile_type: Option<ProfileType>,
+    location: Option<PathBuf>,
+    machine_name: Option<String>,
+    notes: Option<String>,
+    active: Option<bool>,
+}
+
+// Separators of list cells typed by hand; exports write JSON arrays so
+// items can hold these
+const TAG_SEPARATOR: &str = ",";
+const TASK_SEPARATOR: &str = "\n";
+
+pub fn export(data: &AppsData, format: Format) -> Result<String> {
+    let mut apps: Vec<App> = data.apps.values().cloned().collect();
+    apps.sort_by(|a, b| a.name.cmp(&b.name));
+
+    match format {
+        Format::Json => Ok(serde_json::to_string_pretty(&Document { apps })? + "\n"),
+        Format::Yaml => Ok(serde_yaml::to_string(&Document { apps })?),
+        Format::Toml => Ok(toml::to_string_pretty(&Document { apps })?),
+        Format::Csv => export_csv(&apps),
+    }
+}
+
+fn export_csv(apps: &[App]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for app in apps {
+        let (tags, aliases, tasks) = (list_cell(&app.tags)?, list_cell(&app.aliases)?, list_cell(&app.tasks)?);
+        let layout = if app.layout.is_empty() { String::new() } else { serde_json::to_string(&app.layout)? };
+        let row = |profile: Option<&AppProfile>| Row {
+            name: app.name.clone(),
+            tags: tags.clone(),
+            aliases: aliases.clone(),
+            github_repo: app.github_repo.clone(),
+            opener: app.opener,
+            layout: layout.clone(),
+            tasks: tasks.clone(),
+            created_at: app.created_at.clone(),
+            updated_at: app.updated_at.clone(),
+            profile_type: profile.map(|p| p.profile_type),
+            location: profile.map(|p| p.location.clone()),
+            machine_name: profile.and_then(|p| p.machine_name.clone()),
+            notes: profile.and_then(|p| p.notes.clone()),
+            active: profile.map(|p| p.active),
+        };
+        if app.profiles.is_empty() {
+            writer.serialize(row(None))?;
+        }
+        for profile in &app.profiles {
+            writer.serialize(row(Some(profile)))?;
+        }
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Parse an export and check it is a usable registry. All problems are
+/// reported together.
+pub fn import(content: &str, format: Format) -> Result<AppsData> {
+    let apps = match format {
+        Format::Json => serde_json::from_str::<Document>(content).context("Invalid JSON")?.apps,
+        Format::Yaml => serde_yaml::from_str::<Document>(content).context("Invalid YAML")?.apps,
+        Format::Toml => toml::from_str::<Document>(content).context("Invalid TOML")?.apps,
+        Format::Csv => import_csv(content)?,
+    };
+
+    let problems = validate(&apps)?;
+    if !problems.is_empty() {
+        return Err(anyhow::anyhow!("Import rejected:\n  {}", problems.join("\n  ")));
+    }
+
+    let mut data = AppsData::default();
+    for mut app in apps {
+        if !app.profiles.is_empty() && !app.profiles.iter().any(|p| p.active) {
+            app.profiles[0].active = true;
+        }
+        data.apps.insert(app.name.clone(), app);
+    }
+    Ok(data)
+}
+
+fn import_csv(content: &str) -> Result<Vec<App>> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let mut apps: Vec<App> = Vec::new();
+    let mut first_rows: HashMap<String, usize> = HashMap::new();
+    let mut problems = Vec::new();
+
+    for (i, row) in reader.deserialize::<Row>().enumerate() {
+        // Line numbers as a spreadsheet shows them, after the header
+        let line = i + 2;
+        let row = row.with_context(|| format!("Invalid CSV on line {}", line))?;
+
+        let layout = if row.layout.trim().is_empty() {
+            Vec::new()
+        } else {
+            serde_json::from_str(&row.layout).with_context(|| format!("Invalid layout on line {}", line))?
+        };
+        let app = App {
+            name: row.name.trim().to_string(),
+            profiles: Vec::new(),
+            tags: parse_list(&row.tags, TAG_SEPARATOR),
+            aliases: parse_list(&row.aliases, TAG_SEPARATOR),
+            github_repo: row.github_repo.filter(|r| !r.trim().is_empty()),
+            opener: row.opener,
+            layout,
+            tasks: parse_list(&row.tasks, TASK_SEPARATOR),
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        };
+        let index = match first_rows.get(&app.name) {
+            Some(&index) => {
+                let existing = &apps[index];
+                if existing.tags != app.tags
+                    || existing.aliases != app.aliases
+                    || existing.github_repo != app.github_repo
+                    || existing.opener != app.opener
+                    || existing.layout != app.layout
+                    || existing.tasks != app.tasks
+                    || existing.created_at != app.created_at
+                    || existing.updated_at != app.updated_at
+                {
+                    problems.push(format!("line {}: app fields of {} differ from its earlier row", line, app.name));
+                }
+                index
+            }
+            None => {
+                first_rows.insert(app.name.clone(), apps.len());
+                apps.push(app);
+                apps.len() - 1
+            }
+        };
+
+        match (row.profile_type, row.location) {
+            (Some(profile_type), Some(location)) => apps[index].profiles.push(AppProfile {
+                profile_type,
+                location,
+                machine_name: row.machine_name.filter(|m| !m.trim().is_empty()),
+                notes: row.notes.filter(|n| !n.trim().is_empty()),
+                active: row.active.unwrap_or(false),
+            }),
+            (None, None) => {}
+            _ => problems.push(format!("line {}: profile_type and location must be given together", line)),
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(anyhow::anyhow!("Import rejected:\n  {}", problems.join("\n  ")));
+    }
+    Ok(apps)
+}
+
+/// A list as a CSV cell: a JSON array, or empty for no items.
+fn list_cell(items: &[String]) -> Result<String> {
+    if items.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(serde_json::to_string(items)?)
+}
+
+/// Read a list cell: a JSON array of strings as exports write it, kept
+/// exactly; anything else is taken as typed by hand, `separator`-joined
+/// with items trimmed, so a task like `[WIP] fix` is just text.
+fn parse_list(cell: &str, separator: &str) -> Vec<String> {
+    if let Ok(items) = serde_json::from_str(cell) {
+        return items;
+    }
+    cell.split(separator)
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn validate(apps: &[App]) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+    let mut seen: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for (i, app) in apps.iter().enumerate() {
+        let mut app_problems = Vec::new();
+        validate::check_app(&serde_json::to_value(app)?, &format!("$.apps[{}]", i), &mut app_problems);
+        problems.extend(app_problems.iter().map(|p| p.to_string()));
+        *seen.entry(&app.name).or_default() += 1;
+    }
+
+    for (name, count) in seen {
+        if count > 1 {
+            problems.push(format!("{}: appears {} times", name, count));
+        }
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes() -> App {
+        serde_json::from_value(serde_json::json!({
+            "name": "notes",
+            "tags": ["rust, cli", "web"],
+            "aliases": ["nt"],
+            "tasks": ["first line\nsecond line", "say \"hi\", then leave", "[WIP] fix", "trailing space "],
+            "github_repo": null,
+            "profiles": [{ "profile_type": "dev", "location": "/src/notes", "machine_name": null, "notes": "a, b\nc", "active": true }],
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn csv_round_trips_items_with_commas_quotes_and_newlines() {
+        let mut data = AppsData::default();
+        data.apps.insert("notes".to_string(), notes());
+        let bare: App = serde_json::from_value(serde_json::json!({
+            "name": "bare",
+            "github_repo": null,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        data.apps.insert("bare".to_string(), bare);
+
+        let csv = export(&data, Format::Csv).unwrap();
+        let imported = import(&csv, Format::Csv).unwrap();
+        assert_eq!(imported.apps, data.apps);
+    }
+
+    #[test]
+    fn hand_typed_list_cells_are_split_and_trimmed() {
+        assert_eq!(parse_list("rust, cli ,, web", TAG_SEPARATOR), ["rust", "cli", "web"]);
+        assert_eq!(parse_list("[WIP] fix\nship it", TASK_SEPARATOR), ["[WIP] fix", "ship it"]);
+        assert_eq!(parse_list("[\"a, b\"]", TAG_SEPARATOR), ["a, b"]);
+        assert!(parse_list("", TAG_SEPARATOR).is_empty());
+    }
+}
@@ -0,0 +1,240 @@
+use chrono::DateTime;
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{Opener, ProfileType, normalize_name, tmux};
+
+/// Something wrong at one place in a document, e.g.
+/// `$.apps.foo.profiles[1].profile_type`.
+pub struct Problem {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+const APP_FIELDS: &[&str] = &["name", "profiles", "tags", "aliases", "github_repo", "opener", "layout", "tasks", "created_at", "updated_at"];
+const PROFILE_FIELDS: &[&str] = &["profile_type", "location", "machine_name", "notes", "active"];
+
+/// Check a whole apps.json document (already migrated to the current
+/// schema version) and collect every problem rather than stopping at the
+/// first, as deserializing does.
+pub fn check_document(document: &Value) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let Some(root) = expect_object(document, "$", &mut problems) else {
+        return problems;
+    };
+
+    if root.get("schema_version").is_some_and(|v| !v.is_u64()) {
+        problems.push(problem("$.schema_version", "must be a non-negative integer"));
+    }
+    match root.get("apps") {
+        None => problems.push(problem("$", "missing field `apps`")),
+        Some(apps) => {
+            if let Some(apps) = expect_object(apps, "$.apps", &mut problems) {
+                for (key, app) in apps {
+                    let path = child("$.apps", key);
+                    check_app(app, &path, &mut problems);
+                    if let Some(name) = app.get("name").and_then(Value::as_str)
+                        && name != key
+                    {
+                        problems.push(problem(&child(&path, "name"), &format!("\"{}\" does not match its key \"{}\"", name, key)));
+                    }
+                }
+                check_alias_collisions(apps, &mut problems);
+            }
+        }
+    }
+    problems
+}
+
+/// Check one app object found at `path`.
+pub fn check_app(app: &Value, path: &str, problems: &mut Vec<Problem>) {
+    let Some(fields) = expect_object(app, path, problems) else {
+        return;
+    };
+    unknown_fields(fields, APP_FIELDS, path, problems);
+
+    match fields.get("name") {
+        Some(Value::String(name)) if name.trim().is_empty() => problems.push(problem(&child(path, "name"), "must not be empty")),
+        Some(Value::String(_)) => {}
+        Some(_) => problems.push(problem(&child(path, "name"), "must be a string")),
+        None => problems.push(problem(path, "missing field `name`")),
+    }
+    for field in ["tags", "aliases", "tasks"] {
+        if let Some(list) = fields.get(field) {
+            check_string_list(list, &child(path, field), problems);
+        }
+    }
+    if let Some(repo) = fields.get("github_repo")
+        && !(repo.is_string() || repo.is_null())
+    {
+        problems.push(problem(&child(path, "github_repo"), "must be a string or null"));
+    }
+    if let Some(opener) = fields.get("opener")
+        && !opener.is_null()
+        && serde_json::from_value::<Opener>(opener.clone()).is_err()
+    {
+        problems.push(problem(&child(path, "opener"), &format!("{} is not an opener; expected one of {}", opener, value_names::<Opener>())));
+    }
+    if let Some(layout) = fields.get("layout")
+        && let Err(e) = serde_json::from_value::<Vec<tmux::Window>>(layout.clone())
+    {
+        problems.push(problem(&child(path, "layout"), &format!("must be a list of windows with a name and panes: {}", e)));
+    }
+    for field in ["created_at", "updated_at"] {
+        match fields.get(field) {
+            Some(Value::String(value)) if DateTime::parse_from_rfc3339(value).is_err() => problems.push(problem(
+                &child(path, field),
+                &format!("\"{}\" is not an RFC 3339 timestamp (e.g. 2024-01-31T12:00:00Z)", value),
+            )),
+            Some(Value::String(_)) => {}
+            Some(_) => problems.push(problem(&child(path, field), "must be a string")),
+            None => problems.push(problem(path, &format!("missing field `{}`", field))),
+        }
+    }
+
+    let Some(profiles) = fields.get("profiles") else {
+        return;
+    };
+    let profiles_path = child(path, "profiles");
+    let Some(profiles) = profiles.as_array() else {
+        problems.push(problem(&profiles_path, "must be an array"));
+        return;
+    };
+    for (i, profile) in profiles.iter().enumerate() {
+        let profile_path = format!("{}[{}]", profiles_path, i);
+        check_profile(profile, &profile_path, problems);
+
+        let slot = |p: &Value| (p.get("profile_type").cloned(), p.get("machine_name").cloned().unwrap_or(Value::Null));
+        if profile.is_object() && profiles[..i].iter().any(|p| p.is_object() && slot(p) == slot(profile)) {
+            problems.push(problem(&profile_path, "duplicates an earlier profile with the same type and machine"));
+        }
+    }
+    let active = profiles.iter().filter(|p| p.get("active") == Some(&Value::Bool(true))).count();
+    if active > 1 {
+        problems.push(problem(&profiles_path, &format!("{} profiles are active; at most one may be", active)));
+    }
+}
+
+/// Aliases must not name another app, or be another app's alias, since
+/// lookups could then go either way.
+fn check_alias_collisions(apps: &Map<String, Value>, problems: &mut Vec<Problem>) {
+    let mut names: HashMap<String, &str> = HashMap::new();
+    for key in apps.keys() {
+        names.insert(normalize_name(key), key);
+    }
+    for (key, app) in apps {
+        let Some(aliases) = app.get("aliases").and_then(Value::as_array) else {
+            continue;
+        };
+        for (i, alias) in aliases.iter().enumerate() {
+            let Some(alias) = alias.as_str() else {
+                continue;
+            };
+            let path = format!("{}[{}]", child(&child("$.apps", key), "aliases"), i);
+            match names.get(&normalize_name(alias)) {
+                Some(owner) if owner != key => {
+                    problems.push(problem(&path, &format!("\"{}\" is already used by {}", alias, owner)));
+                }
+                _ => {
+                    names.insert(normalize_name(alias), key);
+                }
+            }
+        }
+    }
+}
+
+fn check_profile(profile: &Value, path: &str, problems: &mut Vec<Problem>) {
+    let Some(fields) = expect_object(profile, path, problems) else {
+        return;
+    };
+    unknown_fields(fields, PROFILE_FIELDS, path, problems);
+
+    match fields.get("profile_type") {
+        Some(value) if serde_json::from_value::<ProfileType>(value.clone()).is_err() => {
+            problems.push(problem(
+                &child(path, "profile_type"),
+                &format!("{} is not a profile type; expected one of {}", value, value_names::<ProfileType>()),
+            ));
+        }
+        Some(_) => {}
+        None => problems.push(problem(path, "missing field `profile_type`")),
+    }
+    match fields.get("location") {
+        Some(Value::String(location)) if location.is_empty() => problems.push(problem(&child(path, "location"), "must not be empty")),
+        Some(Value::String(_)) => {}
+        Some(_) => problems.push(problem(&child(path, "location"), "must be a string")),
+        None => problems.push(problem(path, "missing field `location`")),
+    }
+    for field in ["machine_name", "notes"] {
+        if let Some(value) = fields.get(field)
+            && !(value.is_string() || value.is_null())
+        {
+            problems.push(problem(&child(path, field), "must be a string or null"));
+        }
+    }
+    match fields.get("active") {
+        Some(Value::Bool(_)) => {}
+        Some(_) => problems.push(problem(&child(path, "active"), "must be true or false")),
+        None => problems.push(problem(path, "missing field `active`")),
+    }
+}
+
+fn check_string_list(list: &Value, path: &str, problems: &mut Vec<Problem>) {
+    let Some(items) = list.as_array() else {
+        problems.push(problem(path, "must be an array of strings"));
+        return;
+    };
+    for (i, item) in items.iter().enumerate() {
+        if !item.is_string() {
+            problems.push(problem(&format!("{}[{}]", path, i), "must be a string"));
+        }
+    }
+}
+
+fn unknown_fields(fields: &Map<String, Value>, known: &[&str], path: &str, problems: &mut Vec<Problem>) {
+    for key in fields.keys() {
+        if !known.contains(&key.as_str()) {
+            problems.push(problem(&child(path, key), &format!("unknown field; expected one of {}", known.join(", "))));
+        }
+    }
+}
+
+fn expect_object<'a>(value: &'a Value, path: &str, problems: &mut Vec<Problem>) -> Option<&'a Map<String, Value>> {
+    let object = value.as_object();
+    if object.is_none() {
+        problems.push(problem(path, "must be an object"));
+    }
+    object
+}
+
+/// `path.key`, or `path["key"]` when the key isn't a plain identifier.
+fn child(path: &str, key: &str) -> String {
+    let plain = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        format!("{}.{}", path, key)
+    } else {
+        format!("{}[{}]", path, Value::String(key.to_string()))
+    }
+}
+
+fn problem(path: &str, message: &str) -> Problem {
+    Problem { path: path.to_string(), message: message.to_string() }
+}
+
+/// The accepted spellings of a value enum, e.g. `dev, installed, binary, config`.
+fn value_names<T: ValueEnum>() -> String {
+    T::value_variants()
+        .iter()
+        .filter_map(|v| v.to_possible_value())
+        .map(|v| v.get_name().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
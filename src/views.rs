@@ -0,0 +1,65 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::storage;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Name,
+    /// Most recently updated first
+    Updated,
+    /// Most recently created first
+    Created,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewFormat {
+    #[default]
+    Full,
+    Oneline,
+}
+
+/// A saved `--where` query together with how to show its results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct View {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#where: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<SortKey>,
+    #[serde(default)]
+    pub format: ViewFormat,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+/// Names `view` takes as subcommands, which a saved view can't shadow
+pub const RESERVED_NAMES: &[&str] = &["save", "list", "rm", "remove", "help"];
+
+/// The saved views, by name.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Views {
+    #[serde(default)]
+    pub views: BTreeMap<String, View>,
+}
+
+impl Views {
+    pub fn load(path: &Path) -> Result<Views> {
+        if !path.exists() {
+            return Ok(Views::default());
+        }
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid views file {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        storage::write_atomic(path, content.as_bytes())
+    }
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::path::{Component, Path, PathBuf};
+
+/// A root folder of the workspace and the name VS Code shows for it.
+pub struct Folder {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// The workspace file for `folders`, with paths relative to `base` (the
+/// directory the file is in). `existing` is the file's current content:
+/// its folders are replaced, everything else (settings, extensions, ...)
+/// is kept as it was, so regenerating an unchanged set changes nothing.
+pub fn generate(existing: Option<&str>, folders: &[Folder], base: &Path) -> Result<String> {
+    let mut workspace = match existing.filter(|content| !content.trim().is_empty()) {
+        Some(content) => match serde_json::from_str(content) {
+            Ok(Value::Object(workspace)) => workspace,
+            Ok(_) => return Err(anyhow::anyhow!("expected a JSON object")),
+            Err(e) => return Err(anyhow::anyhow!("{} (comments and trailing commas aren't supported)", e)),
+        },
+        None => Map::new(),
+    };
+
+    let folders = folders
+        .iter()
+        .map(|folder| json!({ "path": relative(&folder.path, base).to_string_lossy(), "name": folder.name }))
+        .collect();
+    workspace.insert("folders".to_string(), Value::Array(folders));
+    workspace.entry("settings").or_insert_with(|| json!({}));
+
+    // Tabs, as VS Code writes these files
+    let mut content = Vec::new();
+    let mut serializer = serde_json::Serializer::with_formatter(&mut content, serde_json::ser::PrettyFormatter::with_indent(b"\t"));
+    workspace.serialize(&mut serializer)?;
+    content.push(b'\n');
+    Ok(String::from_utf8(content)?)
+}
+
+/// `path` relative to `base`, or as is when they share no root (e.g. on
+/// different drives).
+fn relative(path: &Path, base: &Path) -> PathBuf {
+    let path_components: Vec<Component> = path.components().collect();
+    let base_components: Vec<Component> = base.components().collect();
+    let common = path_components.iter().zip(&base_components).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return path.to_path_buf();
+    }
+
+    let mut relative: PathBuf = base_components[common..].iter().map(|_| Component::ParentDir).collect();
+    relative.extend(&path_components[common..]);
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
@@ -0,0 +1,60 @@
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// A fresh, empty directory under the system temp dir for one test.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("apps-helper-test-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run apps-helper with its data in `home`, failing the test if it fails.
+pub fn run(home: &Path, args: &[&str]) -> String {
+    succeeded(args, try_run(home, args))
+}
+
+/// Like run, answering prompts with `input`.
+pub fn run_with_input(home: &Path, args: &[&str], input: &str) -> String {
+    let mut child = command(home, args).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    succeeded(args, child.wait_with_output().unwrap())
+}
+
+pub fn try_run(home: &Path, args: &[&str]) -> Output {
+    command(home, args).output().unwrap()
+}
+
+fn command(home: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_apps-helper"));
+    command.args(args).env("APPS_HELPER_HOME", home).env("RUST_BACKTRACE", "0");
+    command
+}
+
+fn succeeded(args: &[&str], output: Output) -> String {
+    assert!(
+        output.status.success(),
+        "apps-helper {} failed:\n{}{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Names of the registered apps, sorted.
+pub fn app_names(home: &Path) -> Vec<String> {
+    app_names_in(&home.join("apps.json"))
+}
+
+/// Names of the apps in the JSON `data_file`, sorted.
+pub fn app_names_in(data_file: &Path) -> Vec<String> {
+    let content = std::fs::read_to_string(data_file).unwrap();
+    let data: serde_json::Value = serde_json::from_str(&content).unwrap();
+    let mut names: Vec<String> = data["apps"].as_object().unwrap().keys().cloned().collect();
+    names.sort();
+    names
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use common::{app_names, run, scratch_dir, try_run};
+
+#[test]
+fn merge_refuses_an_alias_that_another_app_already_uses() {
+    let root = scratch_dir("merge-collision");
+    let (a, b) = (root.join("a"), root.join("b"));
+    let dir = root.to_str().unwrap();
+
+    run(&a, &["app", "add", "notes", "--dir", dir]);
+    run(&b, &["app", "add", "notebook", "--dir", dir]);
+    run(&b, &["app", "--get", "notebook", "alias", "add", "notes"]);
+
+    let other = b.join("apps.json");
+    let output = try_run(&a, &["merge", other.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'notes' of notebook is already used by notes"), "{}", stderr);
+    assert_eq!(app_names(&a), ["notes"]);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,61 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{run, scratch_dir};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// A script that records its working directory and arguments, one per
+/// line, in `out`.
+fn stub(root: &Path, out: &Path) -> String {
+    let script = root.join("stub.sh");
+    let body = format!("#!/bin/sh\npwd > '{}'\nfor arg in \"$@\"; do echo \"$arg\" >> '{}'; done\n", out.display(), out.display());
+    std::fs::write(&script, body).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    script.to_str().unwrap().to_string()
+}
+
+#[test]
+fn open_runs_the_configured_command_in_the_app_directory() {
+    let root = scratch_dir("open");
+    let home = root.join("home");
+    let app_dir = root.join("My Apps").join("notes");
+    std::fs::create_dir_all(&app_dir).unwrap();
+    let out = root.join("args.txt");
+    let stub = stub(&root, &out);
+
+    run(&home, &["app", "add", "notes", "--dir", app_dir.to_str().unwrap()]);
+    run(&home, &["config", "set", "open.editor", &format!("'{}' {{path}} --title={{name}}", stub)]);
+    run(&home, &["open", "notes", "--with", "editor"]);
+
+    let recorded = std::fs::read_to_string(&out).unwrap();
+    let app_dir = app_dir.to_str().unwrap();
+    assert_eq!(recorded, format!("{}\n{}\n--title=notes\n", app_dir, app_dir));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn open_uses_the_app_opener_until_it_is_cleared() {
+    let root = scratch_dir("open-opener");
+    let home = root.join("home");
+    let out = root.join("args.txt");
+    let stub = stub(&root, &out);
+    let dir = root.to_str().unwrap();
+
+    run(&home, &["app", "add", "notes", "--dir", dir]);
+    run(&home, &["config", "set", "open.editor", &format!("'{}' editor", stub)]);
+    run(&home, &["config", "set", "open.terminal", &format!("'{}' terminal", stub)]);
+    run(&home, &["config", "set", "open.default", "editor"]);
+
+    run(&home, &["app", "--get", "notes", "opener", "terminal"]);
+    run(&home, &["open", "notes"]);
+    assert!(std::fs::read_to_string(&out).unwrap().ends_with("\nterminal\n"));
+
+    run(&home, &["app", "--get", "notes", "opener"]);
+    run(&home, &["open", "notes"]);
+    assert!(std::fs::read_to_string(&out).unwrap().ends_with("\neditor\n"));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{app_names_in, run, run_with_input, scratch_dir};
+
+#[test]
+fn registries_in_one_directory_keep_their_own_views() {
+    let root = scratch_dir("registries-views");
+    let home = root.join("home");
+    let work = root.join("reg").join("work.json");
+    let personal = root.join("reg").join("personal.json");
+    let (work, personal) = (work.to_str().unwrap(), personal.to_str().unwrap());
+
+    run(&home, &["--data-file", work, "view", "save", "rusty", "--where", "tag:rust"]);
+    run(&home, &["--data-file", personal, "view", "save", "home", "--where", "tag:home"]);
+
+    let work_views = run(&home, &["--data-file", work, "view", "list"]);
+    assert!(work_views.contains("rusty") && !work_views.contains("home"), "{}", work_views);
+    let personal_views = run(&home, &["--data-file", personal, "view", "list"]);
+    assert!(personal_views.contains("home") && !personal_views.contains("rusty"), "{}", personal_views);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn registries_in_one_directory_keep_their_own_history() {
+    let root = scratch_dir("registries-history");
+    let home = root.join("home");
+    let reg = root.join("reg");
+    let work = reg.join("work.json");
+    let personal = reg.join("personal.json");
+    let (work, personal) = (work.to_str().unwrap(), personal.to_str().unwrap());
+    let dir = root.to_str().unwrap();
+
+    run(&home, &["--data-file", work, "app", "add", "workapp", "--dir", dir]);
+    run(&home, &["--data-file", personal, "app", "add", "homeapp", "--dir", dir]);
+    run(&home, &["--data-file", personal, "app", "add", "homeapp2", "--dir", dir]);
+    run(&home, &["--data-file", work, "app", "add", "workapp2", "--dir", dir]);
+
+    assert_eq!(app_names_in(&reg.join("work.json")), ["workapp", "workapp2"]);
+    assert_eq!(app_names_in(&reg.join("personal.json")), ["homeapp", "homeapp2"]);
+    let log = run(&home, &["--data-file", work, "log"]);
+    assert!(log.contains("workapp") && !log.contains("homeapp"), "{}", log);
+
+    // Undo rolls back this registry's last change, not the other's
+    run_with_input(&home, &["--data-file", work, "undo"], "y\n");
+    assert_eq!(app_names_in(&reg.join("work.json")), ["workapp"]);
+    assert_eq!(app_names_in(&reg.join("personal.json")), ["homeapp", "homeapp2"]);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::{run, scratch_dir, try_run};
+use std::path::Path;
+
+fn apps(data_file: &Path) -> serde_json::Value {
+    let content = std::fs::read_to_string(data_file).unwrap();
+    serde_json::from_str::<serde_json::Value>(&content).unwrap()["apps"].clone()
+}
+
+#[test]
+fn json_survives_a_round_trip_through_sqlite() {
+    let root = scratch_dir("storage-round-trip");
+    let home = root.join("home");
+    let json = root.join("reg").join("work.json");
+    let db = root.join("reg").join("work.db");
+    let (json_arg, db_arg) = (json.to_str().unwrap(), db.to_str().unwrap());
+    let dir = root.to_str().unwrap();
+
+    run(&home, &["--data-file", json_arg, "app", "add", "notes", "--dir", dir, "--tags", "rust, cli"]);
+    run(&home, &["--data-file", json_arg, "app", "add", "scratch", "--dir", dir]);
+    run(&home, &["--data-file", json_arg, "app", "--get", "notes", "add-task", "ship it, then \"rest\""]);
+    run(&home, &["--data-file", json_arg, "app", "--get", "notes", "alias", "add", "nt"]);
+    run(&home, &["--data-file", json_arg, "app", "--get", "notes", "opener", "terminal"]);
+    run(&home, &["--data-file", json_arg, "app", "--get", "notes", "layout", "add", "main", "--run", "cargo watch"]);
+    run(&home, &["--data-file", json_arg, "app", "--get", "notes", "profile", "add", "--type", "binary", "--location", "/usr/local/bin/notes", "--notes", "release build"]);
+    let before = apps(&json);
+
+    run(&home, &["--data-file", json_arg, "storage", "migrate", "--to", "sqlite"]);
+    assert!(db.exists() && !json.exists());
+
+    // Saves on SQLite write only what changed and still read back whole
+    run(&home, &["--data-file", db_arg, "app", "--get", "scratch", "add-task", "delete me"]);
+    run(&home, &["--data-file", db_arg, "app", "add", "later", "--dir", dir]);
+
+    run(&home, &["--data-file", db_arg, "storage", "migrate", "--to", "json"]);
+    assert!(json.exists() && !db.exists());
+    let after = apps(&json);
+    assert_eq!(after["notes"], before["notes"]);
+    assert_eq!(after["scratch"]["tasks"], serde_json::json!(["delete me"]));
+    assert!(after["later"].is_object());
+
+    // Going back and forth again needs no --force
+    run(&home, &["--data-file", json_arg, "storage", "migrate", "--to", "sqlite"]);
+    run(&home, &["--data-file", db_arg, "storage", "migrate", "--to", "json"]);
+    assert_eq!(apps(&json), after);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn migrate_refuses_to_overwrite_a_target_without_force() {
+    let root = scratch_dir("storage-force");
+    let home = root.join("home");
+    let json = root.join("reg").join("work.json");
+    let db = root.join("reg").join("work.db");
+    let json_arg = json.to_str().unwrap();
+
+    run(&home, &["--data-file", json_arg, "app", "add", "notes", "--dir", root.to_str().unwrap()]);
+    std::fs::write(&db, "stale").unwrap();
+
+    let output = try_run(&home, &["--data-file", json_arg, "storage", "migrate", "--to", "sqlite"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+
+    run(&home, &["--data-file", json_arg, "storage", "migrate", "--to", "sqlite", "--force"]);
+    let kept = std::fs::read_dir(root.join("reg").join("work.backups")).unwrap().count();
+    assert_eq!(kept, 2, "the stale target and the old JSON file are both kept");
+
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{app_names, run, scratch_dir};
+use std::process::Command;
+
+#[test]
+fn sync_merges_changes_from_two_clones_through_a_bare_remote() {
+    let root = scratch_dir("sync");
+    let remote = root.join("remote.git");
+    let status = Command::new("git").args(["init", "--quiet", "--bare"]).arg(&remote).status().unwrap();
+    assert!(status.success());
+    let remote = remote.to_str().unwrap();
+    let (a, b) = (root.join("a"), root.join("b"));
+    let dir = root.to_str().unwrap();
+
+    run(&a, &["app", "add", "alpha", "--dir", dir]);
+    run(&a, &["git", "init", "--remote", remote]);
+    run(&a, &["sync"]);
+
+    // A second machine with its own history joins and picks up alpha
+    run(&b, &["app", "add", "beta", "--dir", dir]);
+    run(&b, &["git", "init", "--remote", remote]);
+    run(&b, &["sync"]);
+    assert_eq!(app_names(&b), ["alpha", "beta"]);
+
+    // Both change at once; each sync merges app by app
+    run(&a, &["sync"]);
+    run(&a, &["app", "add", "gamma", "--dir", dir]);
+    run(&b, &["app", "add", "delta", "--dir", dir]);
+    run(&a, &["sync"]);
+    run(&b, &["sync"]);
+    run(&a, &["sync"]);
+    assert_eq!(app_names(&a), ["alpha", "beta", "delta", "gamma"]);
+    assert_eq!(app_names(&b), ["alpha", "beta", "delta", "gamma"]);
+
+    // What arrived by sync is in the local history too
+    assert!(run(&a, &["log"]).contains("add app delta"));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn failed_sync_merge_leaves_data_and_history_as_they_were() {
+    let root = scratch_dir("sync-abort");
+    let remote = root.join("remote.git");
+    let status = Command::new("git").args(["init", "--quiet", "--bare"]).arg(&remote).status().unwrap();
+    assert!(status.success());
+    let remote = remote.to_str().unwrap();
+    let (a, b) = (root.join("a"), root.join("b"));
+    let dir = root.to_str().unwrap();
+
+    run(&a, &["app", "add", "alpha", "--dir", dir]);
+    run(&a, &["git", "init", "--remote", remote]);
+    run(&a, &["sync"]);
+    run(&b, &["app", "add", "beta", "--dir", dir]);
+    run(&b, &["git", "init", "--remote", remote]);
+
+    // Refuse the merge commit
+    let hook = b.join(".git/hooks/pre-commit");
+    std::fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let log_before = std::fs::read_to_string(b.join("apps.events.jsonl")).unwrap();
+    assert!(!common::try_run(&b, &["sync"]).status.success());
+    assert_eq!(std::fs::read_to_string(b.join("apps.events.jsonl")).unwrap(), log_before);
+    assert_eq!(app_names(&b), ["beta"]);
+    assert!(!run(&b, &["log"]).contains("alpha"));
+
+    std::fs::remove_file(&hook).unwrap();
+    run(&b, &["sync"]);
+    assert_eq!(app_names(&b), ["alpha", "beta"]);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
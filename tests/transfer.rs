@@ -0,0 +1,22 @@
+mod common;
+
+use common::{app_names, run, run_with_input, scratch_dir};
+
+#[test]
+fn replace_from_stdin_removes_apps_with_yes() {
+    let root = scratch_dir("transfer-replace-stdin");
+    let home = root.join("home");
+    let dir = root.to_str().unwrap();
+    run(&home, &["app", "add", "notes", "--dir", dir]);
+    run(&home, &["app", "add", "scratch", "--dir", dir]);
+    let exported = run(&home, &["export", "--format", "csv"]);
+    let notes_only: String = exported.lines().filter(|line| !line.starts_with("scratch,")).map(|line| format!("{}\n", line)).collect();
+
+    // Without --yes the confirmation reads the already consumed stdin and cancels
+    let output = run_with_input(&home, &["import", "-", "--format", "csv", "--replace"], &notes_only);
+    assert!(output.contains("Import cancelled."));
+    assert_eq!(app_names(&home), ["notes", "scratch"]);
+
+    run_with_input(&home, &["import", "-", "--format", "csv", "--replace", "--yes"], &notes_only);
+    assert_eq!(app_names(&home), ["notes"]);
+}